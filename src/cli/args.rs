@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 /// Command-line arguments structure using `clap`
 #[derive(Parser, Debug)]
@@ -6,10 +7,151 @@ use clap::Parser;
 #[command(about = "A tool to validate service endpoints")]
 pub struct Cli {
     /// Path to the services.json file
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub services_path: Option<String>,
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub config_path: Option<String>,
+    /// Where request history is kept between sessions (defaults to ~/.endpoint_validator_history.json)
+    #[arg(long)]
+    pub history_path: Option<PathBuf>,
+    /// Where logs are appended (defaults to ~/.endpoint_validator.log)
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+    /// Log level or filter directives, e.g. `debug` or `endpoint_validator::ws=trace`
+    /// (defaults to RUST_LOG, then info)
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// Without a subcommand the interactive TUI is started
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Send every configured endpoint, once per preset, and report the results
+    Run(RunArgs),
+    /// Send endpoints from many connections at a target rate and report
+    /// throughput, errors and latency
+    Load(LoadArgs),
+    /// Send generated valid and invalid params to an endpoint and flag
+    /// crashes, timeouts and invalid input that isn't rejected
+    Fuzz(FuzzArgs),
+    /// Serve example responses for every endpoint in services.json, to try
+    /// the TUI or scripts without a backend
+    Mock(MockArgs),
+    /// Write a config with a table for every endpoint in services.json to
+    /// --config-path, with example params commented with their types
+    InitConfig(InitConfigArgs),
+    /// Compare two services.json versions and report breaking and additive
+    /// changes, exiting non-zero if anything breaks
+    DiffSchema(DiffSchemaArgs),
+    /// Check --services-path for duplicate codes and names, dangling refs and
+    /// other structural problems, exiting non-zero on errors
+    Lint,
+    /// Write a Rust module with request and response types for every endpoint
+    /// in --services-path and a typed client around `WsClient`
+    Codegen(CodegenArgs),
+}
+
+/// Where to connect and how to log in
+#[derive(Args, Debug, Clone)]
+pub struct ConnectionArgs {
+    #[arg(long, default_value = "ws://localhost:8443")]
+    pub url: String,
+    #[arg(long, default_value = "")]
+    pub username: String,
+    #[arg(long, default_value = "")]
+    pub password: String,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// Only run cases for this endpoint name
+    #[arg(long)]
+    pub endpoint: Option<String>,
+    /// How long to wait for each response
+    #[arg(long, default_value_t = 5000)]
+    pub timeout_ms: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct LoadArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// Endpoint to send, as `name` or `name=weight`; repeat for a weighted mix
+    #[arg(long = "endpoint", required = true)]
+    pub endpoints: Vec<String>,
+    /// Param set sent for every endpoint, the config defaults if not given
+    #[arg(long)]
+    pub preset: Option<String>,
+    /// Number of concurrent connections, each logged in on its own
+    #[arg(long, default_value_t = 10)]
+    pub connections: usize,
+    /// Target requests per second across all connections
+    #[arg(long, default_value_t = 100.0)]
+    pub rate: f64,
+    /// How long to keep sending, in seconds
+    #[arg(long, default_value_t = 10)]
+    pub duration_secs: u64,
+    /// Responses slower than this count as timeouts
+    #[arg(long, default_value_t = 5000)]
+    pub timeout_ms: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct FuzzArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// Endpoint to fuzz, by name
+    #[arg(long)]
+    pub endpoint: String,
+    /// Seed of the generated params; the same seed sends the same cases
+    /// (random if not given)
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Most cases to send; beyond this a sample picked by the seed is sent
+    #[arg(long, default_value_t = 200)]
+    pub cases: usize,
+    /// Requests without a response by then are flagged
+    #[arg(long, default_value_t = 5000)]
+    pub timeout_ms: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct MockArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8443")]
+    pub listen: String,
+    /// Time between the example frames of stream endpoints
+    #[arg(long, default_value_t = 1000)]
+    pub stream_interval_ms: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct InitConfigArgs {
+    /// Replace the config file if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffSchemaArgs {
+    /// The services.json clients were built against
+    pub old: PathBuf,
+    /// The changed services.json
+    pub new: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CodegenArgs {
+    /// File to write the module to (defaults to stdout)
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Module path `WsClient` is imported from in the generated code
+    #[arg(long, default_value = "crate::ws")]
+    pub ws_module: String,
 }
 
 /// Function to parse command-line arguments
@@ -0,0 +1,148 @@
+use crate::parser::{Field, ParameterMetadata, Type, TypeRegistry};
+use crate::time::{format_date, now_ms, DAY_MS};
+use serde_json::{json, Map, Value};
+
+/// How far examples follow struct refs, which may be recursive
+const MAX_DEPTH: usize = 8;
+
+/// Builds a realistic example value for any type: timestamps near now,
+/// well-formed UUIDs and addresses, the first enum variant, and structs,
+/// lists and tables with every field filled in
+pub struct Examples<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl<'a> Examples<'a> {
+    pub fn new(registry: &'a TypeRegistry) -> Self {
+        Self { registry }
+    }
+
+    /// Example of `ty` as it is sent. Optionals get their inner example.
+    pub fn value(&self, ty: &Type) -> Value {
+        self.value_at(ty, 0)
+    }
+
+    /// Example of `ty` as a raw param value, like the param form and the
+    /// config file keep them: scalars as typed, containers as JSON
+    pub fn raw(&self, ty: &Type) -> String {
+        match self.value(ty) {
+            Value::Null => String::new(),
+            Value::String(s) => s,
+            other => other.to_string(),
+        }
+    }
+
+    /// Raw value for a param the config leaves out: empty for optionals,
+    /// an example otherwise
+    pub fn param_raw(&self, ty: &Type) -> String {
+        match ty {
+            Type::Optional(_) => String::new(),
+            _ => self.raw(ty),
+        }
+    }
+
+    /// Adds the `param_raw` value of every param `values` has none for
+    pub fn fill_missing(&self, params: &[ParameterMetadata], values: &mut Vec<(String, String)>) {
+        for param in params {
+            if !values.iter().any(|(name, _)| *name == param.name) {
+                values.push((param.name.clone(), self.param_raw(&param.ty)));
+            }
+        }
+    }
+
+    fn value_at(&self, ty: &Type, depth: usize) -> Value {
+        match ty {
+            Type::TimeStampMs => json!(now_ms()),
+            Type::Date => json!(format_date(now_ms() as i64 / DAY_MS)),
+            Type::Int => json!(1),
+            Type::BigInt => json!(1000),
+            Type::Numeric => json!(1.5),
+            Type::Boolean => json!(true),
+            Type::String => json!("example"),
+            Type::Bytea => json!("deadbeef"),
+            Type::UUID => json!("123e4567-e89b-42d3-a456-426614174000"),
+            Type::Inet => json!("192.168.0.1"),
+            Type::BlockchainDecimal => json!("1.5"),
+            Type::BlockchainAddress => json!("0x742d35cc6634c0532925a3b844bc454e4438f44e"),
+            Type::BlockchainTransactionHash => {
+                json!("0x88df016429689c079f3b2f6ad39fa052532c56795b733da78a91ebe6a713944b")
+            }
+            Type::Object => json!({}),
+            Type::Unit => Value::Null,
+            Type::Optional(inner) => self.value_at(inner, depth),
+            Type::Vec(_) | Type::DataTable { .. } | Type::Struct { .. } | Type::StructRef(_) if depth >= MAX_DEPTH => {
+                Value::Null
+            }
+            Type::Vec(inner) => json!([self.value_at(inner, depth + 1)]),
+            Type::DataTable { fields, .. } => json!([self.object_at(fields, depth + 1)]),
+            Type::Struct { fields, .. } => self.object_at(fields, depth + 1),
+            Type::StructRef(name) => match self.registry.struct_fields(name) {
+                Some(fields) => self.object_at(fields, depth + 1),
+                None => json!({}),
+            },
+            Type::Enum { variants, .. } => variants.first().map_or(Value::Null, |variant| json!(variant.name)),
+            Type::EnumRef(name) => self
+                .registry
+                .enum_variants(name)
+                .and_then(|variants| variants.first())
+                .map_or(Value::Null, |variant| json!(variant.name)),
+        }
+    }
+
+    fn object_at(&self, fields: &[Field], depth: usize) -> Value {
+        let object: Map<String, Value> = fields
+            .iter()
+            .map(|field| (field.name.clone(), self.value_at(&field.ty, depth)))
+            .collect();
+        Value::Object(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Services;
+
+    fn registry() -> TypeRegistry {
+        let services: Services = serde_json::from_value(json!({
+            "enums": [{"Enum": {"name": "Role", "variants": [{"name": "Admin", "value": 1, "comment": ""}]}}],
+            "services": [{"name": "Users", "id": 1, "endpoints": [{
+                "name": "GetUser",
+                "code": 200,
+                "parameters": [{"name": "user", "ty": {"Struct": {"name": "User", "fields": [
+                    {"name": "id", "ty": "BigInt"},
+                    {"name": "role", "ty": {"EnumRef": "Role"}},
+                    {"name": "tags", "ty": {"Vec": "String"}}
+                ]}}}],
+                "returns": [],
+                "stream_response": null,
+                "description": "",
+                "json_schema": null
+            }]}]
+        }))
+        .unwrap();
+        TypeRegistry::from_services(&services)
+    }
+
+    #[test]
+    fn raw_examples_are_sent_as_their_values() {
+        let registry = registry();
+        let examples = Examples::new(&registry);
+        let types = [
+            Type::StructRef("User".to_string()),
+            Type::Optional(Box::new(Type::StructRef("User".to_string()))),
+            Type::Object,
+            Type::Vec(Box::new(Type::Int)),
+            Type::EnumRef("Role".to_string()),
+            Type::UUID,
+        ];
+        for ty in types {
+            let raw = examples.raw(&ty);
+            assert_eq!(ty.convert_value(&raw).unwrap(), examples.value(&ty), "{:?} from {}", ty, raw);
+        }
+        assert_eq!(
+            examples.value(&Type::StructRef("User".to_string())),
+            json!({"id": 1000, "role": "Admin", "tags": ["example"]})
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use crate::generate::Rng;
+use crate::parser::{EnumVariant, Field, ParameterMetadata, Type, TypeRegistry};
+use serde_json::{json, Value};
+
+/// Length of overlong strings
+const OVERLONG_LEN: usize = 100_000;
+/// Items of the oversized list
+const MANY_ITEMS: usize = 10_000;
+/// Levels of the deeply nested lists and objects
+const NESTING_DEPTH: usize = 200;
+/// How far into structs and lists values are mutated
+const MAX_MUTATION_DEPTH: usize = 3;
+/// How far valid values follow struct refs, which may be recursive
+const MAX_VALUE_DEPTH: usize = 8;
+
+/// What a well-behaved server does with a fuzz case
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expect {
+    /// Well-typed params: any answer will do, errors included
+    Valid,
+    /// Legal but extreme params, like boundaries and huge inputs: any answer will do
+    Edge,
+    /// Params the schema rules out: the answer must be an error
+    Invalid,
+}
+
+impl Expect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Expect::Valid => "valid",
+            Expect::Edge => "edge",
+            Expect::Invalid => "invalid",
+        }
+    }
+}
+
+/// Params for one request and what was done to them
+pub struct FuzzCase {
+    pub description: String,
+    pub expect: Expect,
+    pub params: Vec<Value>,
+}
+
+/// One value changed somewhere inside a param, `path` leads to it from the param
+struct Mutation {
+    path: String,
+    description: String,
+    expect: Expect,
+    value: Value,
+}
+
+impl Mutation {
+    fn new(description: impl Into<String>, expect: Expect, value: Value) -> Self {
+        Self { path: String::new(), description: description.into(), expect, value }
+    }
+
+    fn nested(self, path: &str) -> Self {
+        Self { path: format!("{}{}", path, self.path), ..self }
+    }
+}
+
+/// Generates fuzz cases from the param types of an endpoint
+pub struct Fuzzer<'a> {
+    registry: &'a TypeRegistry,
+    rng: Rng,
+}
+
+impl<'a> Fuzzer<'a> {
+    pub fn new(registry: &'a TypeRegistry, seed: u64) -> Self {
+        Self { registry, rng: Rng::new(seed) }
+    }
+
+    /// A random valid param set, then that set with one param, or one value
+    /// inside it, changed at a time, and with its trailing params left out. `params` must be in the order they are
+    /// sent. Above `limit`, a sample picked by the seed is kept.
+    pub fn cases(&mut self, params: &[ParameterMetadata], limit: usize) -> Vec<FuzzCase> {
+        let baseline: Vec<Value> = params.iter().map(|param| self.valid_value(&param.ty, 0)).collect();
+        let mut cases = Vec::new();
+        for (index, param) in params.iter().enumerate() {
+            // Params are positional, so only trailing ones can be left out
+            // without shifting the others into the wrong slots
+            let dropped = &params[index..];
+            let expect = if dropped.iter().all(|param| matches!(param.ty, Type::Optional(_))) {
+                Expect::Edge
+            } else {
+                Expect::Invalid
+            };
+            let description = match dropped {
+                [_] => format!("{}: missing", param.name),
+                _ => format!("{} and later params: missing", param.name),
+            };
+            cases.push(FuzzCase { description, expect, params: baseline[..index].to_vec() });
+
+            for mutation in self.mutations(&param.ty, &baseline[index], 0) {
+                let mut mutated = baseline.clone();
+                mutated[index] = mutation.value;
+                cases.push(FuzzCase {
+                    description: format!("{}{}: {}", param.name, mutation.path, mutation.description),
+                    expect: mutation.expect,
+                    params: mutated,
+                });
+            }
+        }
+        let mut extra = baseline.clone();
+        extra.push(json!("unexpected"));
+        cases.push(FuzzCase { description: "extra trailing param".to_string(), expect: Expect::Edge, params: extra });
+
+        let limit = limit.saturating_sub(1);
+        if cases.len() > limit {
+            self.rng.shuffle(&mut cases);
+            cases.truncate(limit);
+        }
+        cases.insert(0, FuzzCase { description: "random valid params".to_string(), expect: Expect::Valid, params: baseline });
+        cases
+    }
+
+    /// Fields of a struct or table type, with refs looked up
+    fn struct_fields<'t>(&self, ty: &'t Type) -> Option<&'t [Field]>
+    where
+        'a: 't,
+    {
+        match ty {
+            Type::Struct { fields, .. } | Type::DataTable { fields, .. } => Some(fields),
+            Type::StructRef(name) => self.registry.struct_fields(name),
+            _ => None,
+        }
+    }
+
+    /// Variants of an enum type, with refs looked up
+    fn enum_variants<'t>(&self, ty: &'t Type) -> Option<&'t [EnumVariant]>
+    where
+        'a: 't,
+    {
+        match ty {
+            Type::Enum { variants, .. } => Some(variants),
+            Type::EnumRef(name) => self.registry.enum_variants(name),
+            _ => None,
+        }
+    }
+
+    /// A random value of type `ty`
+    pub fn valid_value(&mut self, ty: &Type, depth: usize) -> Value {
+        let rng = &mut self.rng;
+        match ty {
+            Type::TimeStampMs => json!(1_600_000_000_000 + rng.range(0, 400 * 24 * 3_600_000)),
+            Type::Date => json!(format!("{:04}-{:02}-{:02}", rng.range(2000, 2030), rng.range(1, 12), rng.range(1, 28))),
+            Type::Int => json!(rng.range(-1000, 1000)),
+            Type::BigInt => json!(rng.range(-1_000_000_000, 1_000_000_000)),
+            Type::Numeric => json!(rng.range(-100_000, 100_000) as f64 / 100.0),
+            Type::Boolean => json!(rng.coin()),
+            Type::String => {
+                let len = 1 + rng.below(12) as usize;
+                json!(rng.alphanumeric(len))
+            }
+            Type::Bytea => json!(rng.hex(16)),
+            Type::UUID => json!(random_uuid(rng)),
+            Type::Inet => json!(format!("10.{}.{}.{}", rng.below(256), rng.below(256), rng.below(256))),
+            Type::BlockchainDecimal => json!(format!("{}.{:02}", rng.below(1_000_000), rng.below(100))),
+            Type::BlockchainAddress => json!(format!("0x{}", rng.hex(40))),
+            Type::BlockchainTransactionHash => json!(format!("0x{}", rng.hex(64))),
+            Type::Object => json!({}),
+            Type::Unit => Value::Null,
+            Type::Optional(inner) => {
+                if rng.coin() {
+                    Value::Null
+                } else {
+                    self.valid_value(inner, depth)
+                }
+            }
+            Type::Vec(inner) => {
+                let count = if depth < MAX_VALUE_DEPTH { 1 + rng.below(3) } else { 0 };
+                Value::Array((0..count).map(|_| self.valid_value(inner, depth + 1)).collect())
+            }
+            Type::Enum { .. } | Type::EnumRef(_) => {
+                let variants = self.enum_variants(ty).unwrap_or_default();
+                self.rng.pick(variants).map_or(Value::Null, |variant| json!(variant.name))
+            }
+            Type::DataTable { fields, .. } => {
+                let rows = 1 + rng.below(3);
+                Value::Array((0..rows).map(|_| self.valid_object(fields, depth + 1)).collect())
+            }
+            Type::Struct { .. } | Type::StructRef(_) => match self.struct_fields(ty) {
+                Some(fields) if depth < MAX_VALUE_DEPTH => self.valid_object(fields, depth + 1),
+                _ => json!({}),
+            },
+        }
+    }
+
+    fn valid_object(&mut self, fields: &[Field], depth: usize) -> Value {
+        let object = fields
+            .iter()
+            .map(|field| (field.name.clone(), self.valid_value(&field.ty, depth)))
+            .collect();
+        Value::Object(object)
+    }
+
+    /// Edge and invalid replacements of `valid`, a value of type `ty`
+    fn mutations(&mut self, ty: &Type, valid: &Value, depth: usize) -> Vec<Mutation> {
+        let mut mutations = Vec::new();
+        match ty {
+            Type::Optional(inner) => {
+                mutations.push(Mutation::new("null", Expect::Valid, Value::Null));
+                let valid = if valid.is_null() { self.valid_value(inner, depth) } else { valid.clone() };
+                mutations.extend(self.mutations(inner, &valid, depth));
+                return mutations;
+            }
+            Type::Unit => return mutations,
+            Type::Object => {
+                mutations.push(Mutation::new("empty object", Expect::Edge, json!({})));
+                mutations.push(Mutation::new(
+                    format!("{} nested objects", NESTING_DEPTH),
+                    Expect::Edge,
+                    deeply_nested(json!({}), |inner| json!({ "nested": inner })),
+                ));
+                return mutations;
+            }
+            _ => mutations.push(Mutation::new("null in a non-optional slot", Expect::Invalid, Value::Null)),
+        }
+
+        use Expect::{Edge, Invalid};
+        let mut add = |description: &str, expect: Expect, value: Value| {
+            mutations.push(Mutation::new(description, expect, value));
+        };
+        match ty {
+            Type::Int => {
+                add("i32 minimum", Edge, json!(i32::MIN));
+                add("i32 maximum", Edge, json!(i32::MAX));
+                add("zero", Edge, json!(0));
+                add("above i32", Invalid, json!(i32::MAX as i64 + 1));
+                add("below i32", Invalid, json!(i32::MIN as i64 - 1));
+                add("fraction", Invalid, json!(1.5));
+                add("number as a string", Invalid, json!("1"));
+            }
+            Type::BigInt => {
+                add("i64 minimum", Edge, json!(i64::MIN));
+                add("i64 maximum", Edge, json!(i64::MAX));
+                add("above i64", Invalid, json!(u64::MAX));
+                add("fraction", Invalid, json!(1.5));
+                add("number as a string", Invalid, json!("1"));
+            }
+            Type::TimeStampMs => {
+                add("epoch", Edge, json!(0));
+                add("before the epoch", Edge, json!(-1));
+                add("i64 maximum", Edge, json!(i64::MAX));
+                add("fraction", Invalid, json!(1.5));
+                add("text instead of a timestamp", Invalid, json!("now"));
+            }
+            Type::Numeric => {
+                add("zero", Edge, json!(0.0));
+                add("huge", Edge, json!(1e308));
+                add("huge negative", Edge, json!(-1e308));
+                add("smallest subnormal", Edge, json!(5e-324));
+                add("text instead of a number", Invalid, json!("NaN"));
+            }
+            Type::Boolean => {
+                add("bool as a string", Invalid, json!("true"));
+                add("bool as a number", Invalid, json!(1));
+            }
+            Type::String => {
+                add("empty", Edge, json!(""));
+                add(&format!("{} chars", OVERLONG_LEN), Edge, json!("a".repeat(OVERLONG_LEN)));
+                add("unicode and control chars", Edge, json!("ʕ•ᴥ•ʔ 𝔘𝔫𝔦𝔠𝔬𝔡𝔢 \u{0}\u{1b}[31m\r\n\u{202e}"));
+                add("SQL quote", Edge, json!("' OR '1'='1' --"));
+                add("number instead of a string", Invalid, json!(42));
+                add("list instead of a string", Invalid, json!([]));
+            }
+            Type::Bytea => {
+                add("empty", Edge, json!(""));
+                add(&format!("{} chars", OVERLONG_LEN), Edge, json!("ab".repeat(OVERLONG_LEN / 2)));
+                add("not hex", Edge, json!("zz"));
+                add("number instead of bytes", Invalid, json!(42));
+            }
+            Type::UUID => {
+                add("nil uuid", Edge, json!("00000000-0000-0000-0000-000000000000"));
+                add("malformed uuid", Invalid, json!("not-a-uuid"));
+                add("truncated uuid", Invalid, json!("00000000-0000-0000-0000-00000000000"));
+                add("non-hex uuid", Invalid, json!("zzzzzzzz-zzzz-zzzz-zzzz-zzzzzzzzzzzz"));
+                add("number instead of a uuid", Invalid, json!(42));
+            }
+            Type::Date => {
+                add("earliest date", Edge, json!("0001-01-01"));
+                add("latest date", Edge, json!("9999-12-31"));
+                add("February 30th", Invalid, json!("2023-02-30"));
+                add("month 13", Invalid, json!("2023-13-01"));
+                add("text instead of a date", Invalid, json!("not-a-date"));
+                add("number instead of a date", Invalid, json!(20230101));
+            }
+            Type::Inet => {
+                add("IPv6 loopback", Edge, json!("::1"));
+                add("unspecified address", Edge, json!("0.0.0.0"));
+                add("octet above 255", Invalid, json!("256.0.0.1"));
+                add("text instead of an address", Invalid, json!("not-an-ip"));
+                add("number instead of an address", Invalid, json!(42));
+            }
+            Type::BlockchainDecimal => {
+                add("zero", Edge, json!("0"));
+                add("negative", Edge, json!("-1"));
+                add("2^256", Edge, json!("115792089237316195423570985008687907853269984665640564039457584007913129639936"));
+                add("not a number", Invalid, json!("abc"));
+                add("two decimal points", Invalid, json!("1.2.3"));
+            }
+            Type::BlockchainAddress => {
+                add("zero address", Edge, json!(format!("0x{}", "0".repeat(40))));
+                add("short address", Invalid, json!("0x1234"));
+                add("non-hex address", Invalid, json!(format!("0x{}", "g".repeat(40))));
+            }
+            Type::BlockchainTransactionHash => {
+                add("short hash", Invalid, json!("0x1234"));
+                add("non-hex hash", Invalid, json!(format!("0x{}", "g".repeat(64))));
+            }
+            Type::Enum { .. } | Type::EnumRef(_) => {
+                add("unknown variant", Invalid, json!("NOT_A_VARIANT"));
+                add("number instead of a variant", Invalid, json!(i64::MAX));
+                let variants = self.enum_variants(ty).unwrap_or_default();
+                if let Some(variant) = variants.first() {
+                    let lowercase = variant.name.to_lowercase();
+                    if !variants.iter().any(|other| other.name == lowercase) {
+                        mutations.push(Mutation::new("variant name in the wrong case", Invalid, json!(lowercase)));
+                    }
+                }
+            }
+            Type::Vec(inner) => {
+                add("empty list", Edge, json!([]));
+                let item = valid.get(0).cloned().unwrap_or_else(|| self.valid_value(inner, depth + 1));
+                add(&format!("{} items", MANY_ITEMS), Edge, Value::Array(vec![item.clone(); MANY_ITEMS]));
+                add("string instead of a list", Invalid, json!("not a list"));
+                add(&format!("{} nested lists", NESTING_DEPTH), Invalid, deeply_nested(json!([]), |inner| json!([inner])));
+                if depth < MAX_MUTATION_DEPTH {
+                    for mutation in self.mutations(inner, &item, depth + 1) {
+                        mutations.push(Mutation { value: json!([mutation.value]), ..mutation }.nested("[0]"));
+                    }
+                }
+            }
+            Type::DataTable { fields, .. } => {
+                add("no rows", Edge, json!([]));
+                add("object instead of rows", Invalid, json!({}));
+                let row = valid.get(0).cloned().unwrap_or_else(|| self.valid_object(fields, depth + 1));
+                for mutation in self.field_mutations(fields, &row, depth) {
+                    mutations.push(Mutation { value: json!([mutation.value]), ..mutation }.nested("[0]"));
+                }
+            }
+            Type::Struct { .. } | Type::StructRef(_) => {
+                add("list instead of an object", Invalid, json!([]));
+                if let Some(fields) = self.struct_fields(ty) {
+                    mutations.extend(self.field_mutations(fields, valid, depth));
+                }
+            }
+            Type::Optional(_) | Type::Unit | Type::Object => {}
+        }
+        mutations
+    }
+
+    /// Objects of `fields` with one field left out, one unknown field added,
+    /// or one field mutated
+    fn field_mutations(&mut self, fields: &[Field], valid: &Value, depth: usize) -> Vec<Mutation> {
+        let Some(object) = valid.as_object() else {
+            return Vec::new();
+        };
+        let mut mutations = Vec::new();
+        let mut extra = object.clone();
+        extra.insert("__unexpected".to_string(), json!(1));
+        mutations.push(Mutation::new("unknown field", Expect::Edge, Value::Object(extra)));
+
+        for field in fields {
+            let path = format!(".{}", field.name);
+            let mut missing = object.clone();
+            missing.remove(&field.name);
+            let expect = if matches!(field.ty, Type::Optional(_)) { Expect::Edge } else { Expect::Invalid };
+            mutations.push(Mutation::new("missing", expect, Value::Object(missing)).nested(&path));
+
+            if depth < MAX_MUTATION_DEPTH {
+                let value = object.get(&field.name).cloned().unwrap_or(Value::Null);
+                for mutation in self.mutations(&field.ty, &value, depth + 1) {
+                    let mut mutated = object.clone();
+                    mutated.insert(field.name.clone(), mutation.value);
+                    mutations.push(Mutation { value: Value::Object(mutated), ..mutation }.nested(&path));
+                }
+            }
+        }
+        mutations
+    }
+}
+
+fn random_uuid(rng: &mut Rng) -> String {
+    let hex = rng.hex(32);
+    format!("{}-{}-4{}-a{}-{}", &hex[..8], &hex[8..12], &hex[13..16], &hex[17..20], &hex[20..])
+}
+
+/// `leaf` wrapped `NESTING_DEPTH` times by `wrap`
+fn deeply_nested(leaf: Value, wrap: impl Fn(Value) -> Value) -> Value {
+    (0..NESTING_DEPTH).fold(leaf, |inner, _| wrap(inner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Vec<ParameterMetadata> {
+        let param = |name: &str, ty: Type| ParameterMetadata { name: name.to_string(), ty };
+        vec![
+            param("id", Type::Int),
+            param("tags", Type::Vec(Box::new(Type::String))),
+            param("note", Type::Optional(Box::new(Type::String))),
+        ]
+    }
+
+    fn cases(seed: u64, limit: usize) -> Vec<(String, Expect, Vec<Value>)> {
+        let registry = TypeRegistry::default();
+        Fuzzer::new(&registry, seed)
+            .cases(&params(), limit)
+            .into_iter()
+            .map(|case| (case.description, case.expect, case.params))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_cases() {
+        assert_eq!(cases(42, 20), cases(42, 20));
+        assert_eq!(cases(42, 1000), cases(42, 1000));
+        assert_ne!(cases(42, 20), cases(43, 20));
+    }
+
+    #[test]
+    fn missing_params_are_left_out_from_the_end() {
+        let cases = cases(7, 1000);
+        let (_, _, baseline) = &cases[0];
+        let missing: Vec<(&str, Expect, &[Value])> = cases
+            .iter()
+            .filter(|(description, _, _)| description.ends_with("missing") && !description.contains('.'))
+            .map(|(description, expect, params)| (description.as_str(), *expect, params.as_slice()))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("id and later params: missing", Expect::Invalid, &baseline[..0]),
+                ("tags and later params: missing", Expect::Invalid, &baseline[..1]),
+                ("note: missing", Expect::Edge, &baseline[..2]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+mod example;
+mod fuzz;
+mod rng;
+
+pub use example::*;
+pub use fuzz::*;
+pub use rng::*;
@@ -0,0 +1,5 @@
+mod buffer;
+mod setup;
+
+pub use buffer::*;
+pub use setup::*;
@@ -0,0 +1,45 @@
+use crate::logging::LogBuffer;
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Default log location: `~/.endpoint_validator.log`
+pub fn default_log_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(".endpoint_validator.log")
+}
+
+/// Sends every `tracing` event to the log file at `path`, appending, and to
+/// the returned buffer for the log pane. `level` takes filter directives;
+/// without it `RUST_LOG` is used, then `info`. Logs are flushed until the
+/// guard is dropped.
+pub fn init_logging(path: &Path, level: Option<&str>) -> Result<(LogBuffer, WorkerGuard)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    let (file_writer, guard) = tracing_appender::non_blocking(file);
+    let buffer = LogBuffer::default();
+
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level).with_context(|| format!("Invalid log level `{}`", level))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    // tungstenite traces the raw handshake, login header and password included
+    let filter = filter.add_directive("tungstenite::handshake=debug".parse()?);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(file_writer).with_ansi(false))
+        .with(fmt::layer().with_writer(buffer.clone()).with_ansi(false).without_time().with_target(false))
+        .try_init()
+        .context("Failed to initialize logging")?;
+
+    Ok((buffer, guard))
+}
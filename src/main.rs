@@ -1,13 +1,65 @@
 mod cli;
+mod generate;
+mod logging;
 mod tui;
 mod ws;
 mod parser;
+mod runner;
+mod schema;
+mod stats;
+mod time;
 
+use anyhow::Context;
+use std::process::ExitCode;
+
+// Exit codes are returned rather than passed to `process::exit`, so the log
+// guard drops and flushes the last lines of headless runs
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {    
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     // Parse command-line arguments
     let cli = cli::parse_args();
 
+    // Compares two files of its own, so needs none of the paths below
+    if let Some(cli::Command::DiffSchema(args)) = &cli.command {
+        let old = parser::load_services(&args.old)?;
+        let new = parser::load_services(&args.new)?;
+        let changes = schema::diff_services(&old, &new);
+        schema::print_changes(&changes);
+        let breaking = changes.iter().any(|change| change.kind == schema::ChangeKind::Breaking);
+        return Ok(exit_code(!breaking));
+    }
+    if let Some(cli::Command::Lint) = &cli.command {
+        let services_path = cli.services_path.as_deref().ok_or_else(|| anyhow::anyhow!("lint needs --services-path"))?;
+        let issues = schema::lint_services(&parser::load_services(services_path)?);
+        schema::print_issues(&issues);
+        let errors = issues.iter().any(|issue| issue.severity == schema::Severity::Error);
+        return Ok(exit_code(!errors));
+    }
+    if let Some(cli::Command::Codegen(args)) = &cli.command {
+        let services_path =
+            cli.services_path.as_deref().ok_or_else(|| anyhow::anyhow!("codegen needs --services-path"))?;
+        let code = schema::rust_client(&parser::load_services(services_path)?, &args.ws_module);
+        match &args.output {
+            Some(path) => {
+                std::fs::write(path, code).with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("Wrote {}", path.display());
+            }
+            None => print!("{}", code),
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Serves services.json alone, so needs no config
+    if let Some(cli::Command::Mock(args)) = &cli.command {
+        let services_path = cli.services_path.as_deref().ok_or_else(|| anyhow::anyhow!("mock needs --services-path"))?;
+        let log_file = cli.log_file.clone().unwrap_or_else(logging::default_log_path);
+        let (_logs, _log_guard) = logging::init_logging(&log_file, cli.log_level.as_deref())?;
+        let services = parser::load_services(services_path)?;
+        let (_, endpoint_data) = services.extract_endpoints();
+        runner::run_mock(&endpoint_data, &parser::TypeRegistry::from_services(&services), args).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
     // If paths are provided via command-line, use them; otherwise, fallback to interactive input
     let (services_path, config_path) = match (cli.services_path, cli.config_path) {
         (Some(services), Some(config)) => (services, config),
@@ -17,13 +69,67 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    let log_file = cli.log_file.unwrap_or_else(logging::default_log_path);
+    let (logs, _log_guard) = logging::init_logging(&log_file, cli.log_level.as_deref())?;
+
     let services = parser::load_services(&services_path)?;
     let (endpoint_names, endpoint_data) = services.extract_endpoints();
-  
+    let registry = parser::TypeRegistry::from_services(&services);
+
+    // The config is written here rather than read, so it may not exist yet
+    if let Some(cli::Command::InitConfig(args)) = &cli.command {
+        parser::write_starter_config(&config_path, &endpoint_data, &registry, args.force)?;
+        println!("Wrote {} endpoints to {}", endpoint_data.len(), config_path);
+        return Ok(ExitCode::SUCCESS);
+    }
+
     let config = parser::load_config(&config_path)?;
     let param_defaults = parser::extract_param_defaults(&config.endpoints);
+    // Headless runs report on stderr, the TUI in its log pane
+    for warning in parser::check_param_defaults(&param_defaults, &endpoint_data) {
+        if cli.command.is_some() {
+            eprintln!("Warning: {}", warning);
+        } else {
+            tracing::warn!("{}", warning);
+        }
+    }
+
+    match &cli.command {
+        Some(cli::Command::Run(args)) => {
+            let passed = runner::run_headless(&endpoint_data, &param_defaults, &registry, args).await?;
+            return Ok(exit_code(passed));
+        }
+        Some(cli::Command::Load(args)) => {
+            let clean = runner::run_load(&endpoint_data, &param_defaults, &registry, args).await?;
+            return Ok(exit_code(clean));
+        }
+        Some(cli::Command::Fuzz(args)) => {
+            let clean = runner::run_fuzz(&endpoint_data, &registry, args).await?;
+            return Ok(exit_code(clean));
+        }
+        Some(
+            cli::Command::InitConfig(_)
+            | cli::Command::DiffSchema(_)
+            | cli::Command::Lint
+            | cli::Command::Codegen(_)
+            | cli::Command::Mock(_),
+        )
+        | None => {}
+    }
+
+    let history_path = cli.history_path.unwrap_or_else(tui::history::default_history_path);
+    let history = tui::history::History::load(history_path);
 
     // TUI implementation
-    tui::run(endpoint_names, endpoint_data, param_defaults).await?;
-    Ok(())
+    let keymap = tui::keymap::Keymap::from_config(&config.keymap)?;
+    tui::run(endpoint_names, endpoint_data, registry, param_defaults, history, config_path, keymap, logs).await?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn exit_code(success: bool) -> ExitCode {
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
@@ -5,8 +5,9 @@ use std::fs::{self, File};
 use std::path::Path;
 
 pub fn load_services<P: AsRef<Path>>(path: P) -> Result<Services> {
-    let file = File::open(&path).context("Failed to open services.json file")?;
-    let services: Services = from_reader(file).context("Failed to parse services.json")?;
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Failed to open services file: {}", path.display()))?;
+    let services: Services = from_reader(file).with_context(|| format!("Failed to parse services file: {}", path.display()))?;
     Ok(services)
 }
 
@@ -1,7 +1,11 @@
 mod models;
 mod loader;
+mod registry;
 mod services;
+mod writer;
 
 pub use models::*;
 pub use loader::*;
-pub use services::*;
\ No newline at end of file
+pub use registry::*;
+pub use services::*;
+pub use writer::*;
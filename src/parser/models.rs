@@ -1,5 +1,5 @@
 use serde::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Clone, Debug, Hash, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq)]
 pub struct Field {
@@ -14,6 +14,8 @@ pub struct EnumVariant {
     pub comment: String,
 }
 
+// Variant names mirror the services.json schema
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub enum Type {
     TimeStampMs,
@@ -61,6 +63,12 @@ pub struct EnumData {
     enum_def: EnumDef,
 }
 
+impl EnumData {
+    pub fn definition(&self) -> &EnumDef {
+        &self.enum_def
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EndpointSchema {
     pub name: String,
@@ -85,29 +93,16 @@ pub struct Services {
     pub services: Vec<EndpointsType>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Endpoints(pub Vec<String>);
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorCode {
-    pub code: u32,
-    pub symbol: String,
-    pub message: String,
-    pub source: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorCodes {
-    pub language: String,
-    pub codes: Vec<ErrorCode>,
-}
-
 #[derive(Debug, Clone)]
 pub struct EndpointMetadata {
     pub service_name: String,
     pub method_id: u32,
     pub params: Vec<ParameterMetadata>,
     pub is_stream: bool,
+    pub description: String,
+    pub returns: Vec<ParameterMetadata>,
+    pub stream_response: Option<Type>,
+    pub json_schema: serde_json::Value,
 }
 
 #[derive(Debug, Clone)]
@@ -118,17 +113,66 @@ pub struct ParameterMetadata {
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub keymap: KeymapConfig,
     #[serde(flatten)]
     pub endpoints: HashMap<String, EndpointData>,
 }
 
+/// `[keymap]` table: keys per action name, replacing that action's defaults
+#[derive(Debug, Default, Deserialize)]
+pub struct KeymapConfig {
+    /// Adds hjkl/gG navigation outside of text fields
+    #[serde(default)]
+    pub vim: bool,
+    #[serde(flatten)]
+    pub bindings: HashMap<String, KeyList>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
 #[derive(Debug, Deserialize)]
 pub struct EndpointData {
     pub name: String,
     pub params: HashMap<String, ParamValue>,
+    /// Extra named param sets, e.g. happy path, boundary values, invalid input
+    #[serde(default)]
+    pub presets: BTreeMap<String, HashMap<String, ParamValue>>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Param values from the config for one endpoint, as raw input strings
+#[derive(Debug, Clone)]
+pub struct EndpointDefaults {
+    /// Key of the endpoint's table in the config file
+    pub key: String,
+    /// The table's `name` field
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub presets: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl EndpointDefaults {
+    /// Every param set as `(name, values)`, the defaults first as `default`
+    pub fn param_sets(&self) -> Vec<(String, Vec<(String, String)>)> {
+        let mut sets = vec![("default".to_string(), self.params.clone())];
+        sets.extend(self.presets.iter().cloned());
+        sets
+    }
+
+    /// Index of the first of an endpoint's config keys (see
+    /// `EndpointMetadata::config_keys`) that is the table key or `name`, so a
+    /// match by method code ranks before one by endpoint name
+    pub fn match_rank(&self, config_keys: &[String]) -> Option<usize> {
+        config_keys.iter().position(|key| *key == self.key || *key == self.name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ParamValue {
     String(String),
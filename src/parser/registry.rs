@@ -0,0 +1,81 @@
+use crate::parser::{EnumVariant, Field, Services, Type};
+use std::collections::HashMap;
+
+/// Named types of services.json, so `StructRef` and `EnumRef` can be resolved.
+/// Enums come from the top-level `enums` list, structs from the inline
+/// `Struct` and `DataTable` definitions of every endpoint.
+#[derive(Debug, Default)]
+pub struct TypeRegistry {
+    enums: HashMap<String, Vec<EnumVariant>>,
+    structs: HashMap<String, Vec<Field>>,
+}
+
+impl TypeRegistry {
+    pub fn from_services(services: &Services) -> Self {
+        let mut registry = Self::default();
+        for data in &services.enums {
+            let definition = data.definition();
+            registry.enums.insert(definition.name.clone(), definition.variants.clone());
+        }
+        for service in &services.services {
+            for endpoint in &service.endpoints {
+                let fields = endpoint.parameters.iter().chain(&endpoint.returns);
+                for ty in fields.map(|field| &field.ty).chain(&endpoint.stream_response) {
+                    registry.collect(ty);
+                }
+            }
+        }
+        registry
+    }
+
+    fn collect(&mut self, ty: &Type) {
+        match ty {
+            Type::Struct { name, fields } | Type::DataTable { name, fields } => {
+                self.structs.entry(name.clone()).or_insert_with(|| fields.clone());
+                for field in fields {
+                    self.collect(&field.ty);
+                }
+            }
+            Type::Enum { name, variants } => {
+                self.enums.entry(name.clone()).or_insert_with(|| variants.clone());
+            }
+            Type::Vec(inner) | Type::Optional(inner) => self.collect(inner),
+            _ => {}
+        }
+    }
+
+    pub fn enum_variants(&self, name: &str) -> Option<&[EnumVariant]> {
+        self.enums.get(name).map(Vec::as_slice)
+    }
+
+    pub fn struct_fields(&self, name: &str) -> Option<&[Field]> {
+        self.structs.get(name).map(Vec::as_slice)
+    }
+
+    /// `ty` with known `EnumRef`s and `StructRef`s replaced by their definitions.
+    /// Struct refs are followed `depth` levels deep, since structs may refer to
+    /// themselves; deeper ones are kept as refs.
+    pub fn resolve(&self, ty: &Type, depth: usize) -> Type {
+        let fields = |fields: &[Field], depth: usize| -> Vec<Field> {
+            fields
+                .iter()
+                .map(|field| Field { name: field.name.clone(), ty: self.resolve(&field.ty, depth) })
+                .collect()
+        };
+        match ty {
+            Type::EnumRef(name) => match self.enum_variants(name) {
+                Some(variants) => Type::Enum { name: name.clone(), variants: variants.to_vec() },
+                None => ty.clone(),
+            },
+            Type::StructRef(name) if depth > 0 => match self.struct_fields(name) {
+                Some(found) => Type::Struct { name: name.clone(), fields: fields(found, depth - 1) },
+                None => ty.clone(),
+            },
+            Type::Struct { name, fields: own } => Type::Struct { name: name.clone(), fields: fields(own, depth) },
+            Type::DataTable { name, fields: own } => Type::DataTable { name: name.clone(), fields: fields(own, depth) },
+            Type::Vec(inner) => Type::Vec(Box::new(self.resolve(inner, depth))),
+            Type::Optional(inner) => Type::Optional(Box::new(self.resolve(inner, depth))),
+            _ => ty.clone(),
+        }
+    }
+}
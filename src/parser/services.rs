@@ -1,7 +1,9 @@
-use crate::parser::{Services, EndpointMetadata, ParameterMetadata, Type, ParamValue, EndpointData};
+use crate::parser::{Services, EndpointMetadata, EndpointDefaults, Field, ParameterMetadata, Type, ParamValue, EndpointData};
 use std::collections::HashMap;
+use std::fmt;
 use anyhow::{Result, anyhow};
 use serde_json::{Value, Number, json};
+use tracing::warn;
 
 impl Services {
     pub fn extract_endpoints(&self) -> (Vec<String>, HashMap<String, EndpointMetadata>) {
@@ -10,24 +12,36 @@ impl Services {
 
         for service in &self.services {
             for endpoint in &service.endpoints {
+                if endpoint_data.contains_key(&endpoint.name) {
+                    warn!(
+                        "Endpoint {} ({}) in {} has the name of an earlier endpoint and is skipped, see `lint`",
+                        endpoint.name, endpoint.code, service.name
+                    );
+                    continue;
+                }
                 endpoint_names.push(endpoint.name.clone());
 
-                let param_names_and_types = endpoint
-                    .parameters
-                    .iter()
-                    .map(|param| ParameterMetadata {
-                        name: param.name.clone(),
-                        ty: param.ty.clone(),
-                    })
-                    .collect();
+                let to_metadata = |fields: &[Field]| {
+                    fields
+                        .iter()
+                        .map(|field| ParameterMetadata {
+                            name: field.name.clone(),
+                            ty: field.ty.clone(),
+                        })
+                        .collect()
+                };
 
                 let returns_stream = endpoint.stream_response.is_some();
 
                 let metadata = EndpointMetadata {
                     service_name: service.name.clone(),
-                    method_id: endpoint.code as u32,
-                    params: param_names_and_types,
+                    method_id: endpoint.code,
+                    params: to_metadata(&endpoint.parameters),
                     is_stream: returns_stream,
+                    description: endpoint.description.clone(),
+                    returns: to_metadata(&endpoint.returns),
+                    stream_response: endpoint.stream_response.clone(),
+                    json_schema: endpoint.json_schema.clone(),
                 };
 
                 endpoint_data.insert(endpoint.name.clone(), metadata);
@@ -76,6 +90,13 @@ impl Type {
                     inner_type.convert_value(value)
                 }
             }
+            // Containers are typed as JSON, by hand or as examples and in the config
+            Type::Vec(_) | Type::Struct { .. } | Type::DataTable { .. } | Type::StructRef(_) | Type::Object
+                if is_json_container(value) =>
+            {
+                let json: Value = serde_json::from_str(value).map_err(anyhow::Error::msg)?;
+                self.convert_json(&json)
+            }
             Type::Vec(inner_type) => {
                 let values: Vec<&str> = value.split(',').collect(); // Assuming comma-separated values
                 let converted_values: Result<Vec<Value>, anyhow::Error> = values.iter().map(|v| inner_type.convert_value(v)).collect();
@@ -133,31 +154,330 @@ impl Type {
                 // Assuming StructRef behaves similarly to Struct
                 Ok(Value::String(value.to_string()))
             }
-            Type::Object => Ok(json!(value)), // Assuming object as a string or raw JSON
+            Type::Object => Ok(json!(value)), // Anything but JSON is sent as a string
             Type::Unit => Ok(Value::Null), // Unit type maps to Null in JSON
         }
     }
+
+    /// Converts a JSON value as written by the param form. Strings are raw input
+    /// for `convert_value`, other scalars are converted from their JSON text.
+    pub fn convert_json(&self, value: &Value) -> Result<Value, anyhow::Error> {
+        match (self, value) {
+            (Type::Optional(_), Value::Null) => Ok(Value::Null),
+            (Type::Optional(inner_type), value) => inner_type.convert_json(value),
+            (Type::Vec(inner_type), Value::Array(items)) => {
+                items.iter().map(|item| inner_type.convert_json(item)).collect::<Result<_, _>>().map(Value::Array)
+            }
+            (Type::Struct { fields, .. }, Value::Object(map)) => convert_fields(fields, map),
+            (Type::DataTable { fields, .. }, Value::Array(rows)) => rows
+                .iter()
+                .map(|row| match row {
+                    Value::Object(map) => convert_fields(fields, map),
+                    _ => Err(anyhow!("Table rows must be objects")),
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            (Type::Object, value) => Ok(value.clone()),
+            // Fields of a ref can't be checked without the registry
+            (Type::StructRef(_), Value::Object(_)) => Ok(value.clone()),
+            (Type::StructRef(name), value) if !value.is_string() => {
+                Err(anyhow!("Expected struct {} but got {}", name, value))
+            }
+            (Type::Vec(_) | Type::Struct { .. } | Type::DataTable { .. }, value) => {
+                Err(anyhow!("Expected {} but got {}", self, value))
+            }
+            (_, Value::String(raw)) => self.convert_value(raw),
+            (_, value) => self.convert_value(&value.to_string()),
+        }
+    }
+}
+
+fn is_json_container(value: &str) -> bool {
+    matches!(value.trim_start().chars().next(), Some('[' | '{'))
+}
+
+/// Converts the fields present in `map`, missing fields are left out
+fn convert_fields(fields: &[Field], map: &serde_json::Map<String, Value>) -> Result<Value, anyhow::Error> {
+    let mut converted = serde_json::Map::new();
+    for field in fields {
+        if let Some(value) = map.get(&field.name) {
+            let value = field.ty
+                .convert_json(value)
+                .map_err(|err| anyhow!("Invalid value for field {}: {}", field.name, err))?;
+            converted.insert(field.name.clone(), value);
+        }
+    }
+    Ok(Value::Object(converted))
+}
+
+/// Human readable type names for the UI, e.g. `list<UserProfile>?` instead of
+/// the `Debug` output `Optional(Vec(Struct { .. }))`
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::TimeStampMs => write!(f, "timestamp_ms"),
+            Type::Date => write!(f, "date"),
+            Type::Int => write!(f, "int"),
+            Type::BigInt => write!(f, "bigint"),
+            Type::Numeric => write!(f, "numeric"),
+            Type::Boolean => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Bytea => write!(f, "bytes"),
+            Type::UUID => write!(f, "uuid"),
+            Type::Inet => write!(f, "inet"),
+            Type::Struct { name, .. } | Type::StructRef(name) => write!(f, "{}", name),
+            Type::Object => write!(f, "object"),
+            Type::DataTable { name, .. } => write!(f, "table<{}>", name),
+            Type::Vec(inner) => write!(f, "list<{}>", inner),
+            Type::Unit => write!(f, "()"),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Enum { name, .. } | Type::EnumRef(name) => write!(f, "enum {}", name),
+            Type::BlockchainDecimal => write!(f, "decimal"),
+            Type::BlockchainAddress => write!(f, "address"),
+            Type::BlockchainTransactionHash => write!(f, "tx_hash"),
+        }
+    }
+}
+
+impl EndpointMetadata {
+    /// Params in the order they are sent: sorted by name
+    pub fn sorted_params(&self) -> Vec<ParameterMetadata> {
+        let mut sorted_params = self.params.clone();
+        sorted_params.sort_by(|a, b| a.name.cmp(&b.name));
+        sorted_params
+    }
+
+    /// Names a config table may use to refer to this endpoint: the method code,
+    /// the endpoint name and the service-qualified `Service.endpoint` name
+    pub fn config_keys(&self, endpoint_name: &str) -> Vec<String> {
+        vec![
+            self.method_id.to_string(),
+            endpoint_name.to_string(),
+            format!("{}.{}", self.service_name, endpoint_name),
+        ]
+    }
+
+    /// Converts raw input strings, keyed by param name, into the request params.
+    /// Params without a value are converted from an empty string.
+    pub fn convert_params(&self, values: &[(String, String)]) -> Result<Vec<Value>> {
+        self.sorted_params()
+            .iter()
+            .map(|param| {
+                let value = values
+                    .iter()
+                    .find(|(name, _)| *name == param.name)
+                    .map(|(_, value)| value.as_str())
+                    .unwrap_or("");
+                param.ty
+                    .convert_value(value)
+                    .map_err(|err| anyhow!("Failed to convert value for parameter {}: {}", param.name, err))
+            })
+            .collect()
+    }
 }
 
 pub fn extract_param_defaults(
     endpoints: &HashMap<String, EndpointData>,
-) -> Vec<(String, Vec<(String, String)>)> {
+) -> Vec<EndpointDefaults> {
     let mut result = Vec::new();
 
-    for (method_id, endpoint_data) in endpoints {
-        let mut param_vec = Vec::new();
-        for (param_name, param_value) in &endpoint_data.params {
-            let value_str = match param_value {
-                ParamValue::String(s) => s.clone(),
-                ParamValue::Number(n) => n.to_string(),
-                ParamValue::Bool(b) => b.to_string(),
-                ParamValue::Array(arr) => format!("{:?}", arr),
-                ParamValue::Object(obj) => format!("{:?}", obj),
-            };
-            param_vec.push((param_name.clone(), value_str));
-        }
-        result.push((method_id.clone(), param_vec));
+    for (key, endpoint_data) in endpoints {
+        let presets = endpoint_data.presets
+            .iter()
+            .map(|(preset, params)| (preset.clone(), param_strings(params)))
+            .collect();
+        result.push(EndpointDefaults {
+            key: key.clone(),
+            name: endpoint_data.name.clone(),
+            params: param_strings(&endpoint_data.params),
+            presets,
+        });
     }
 
+    // Config tables come out of a map, keep lookups deterministic
+    result.sort_by(|a, b| a.key.cmp(&b.key));
     result
 }
+
+/// Config defaults for an endpoint, matched by method code, endpoint name or
+/// service-qualified name, see `defaults_index`
+pub fn find_defaults<'a>(
+    param_defaults: &'a [EndpointDefaults],
+    endpoint_name: &str,
+    metadata: &EndpointMetadata,
+) -> Option<&'a EndpointDefaults> {
+    defaults_index(param_defaults, endpoint_name, metadata).map(|index| &param_defaults[index])
+}
+
+/// Index of an endpoint's config defaults. When several tables match, one
+/// matched by method code wins over one matched by name, then by
+/// service-qualified name; `check_param_defaults` warns about the others.
+pub fn defaults_index(param_defaults: &[EndpointDefaults], endpoint_name: &str, metadata: &EndpointMetadata) -> Option<usize> {
+    let config_keys = metadata.config_keys(endpoint_name);
+    param_defaults
+        .iter()
+        .enumerate()
+        .filter_map(|(index, defaults)| Some((defaults.match_rank(&config_keys)?, index)))
+        .min()
+        .map(|(_, index)| index)
+}
+
+/// Problems with the config that would otherwise be silently ignored: tables
+/// matching no endpoint in services.json, tables shadowed by another one for
+/// the same endpoint, and params the endpoint doesn't declare
+pub fn check_param_defaults(
+    param_defaults: &[EndpointDefaults],
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for defaults in param_defaults {
+        let endpoint = endpoint_data
+            .iter()
+            .filter_map(|(name, metadata)| Some((defaults.match_rank(&metadata.config_keys(name))?, name, metadata)))
+            .min_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let Some((_, endpoint_name, metadata)) = endpoint else {
+            warnings.push(format!(
+                "Config entry [{}] (name = \"{}\") matches no endpoint in services.json",
+                defaults.key, defaults.name
+            ));
+            continue;
+        };
+
+        for (i, (set, values)) in defaults.param_sets().into_iter().enumerate() {
+            let section = if i == 0 { "params".to_string() } else { format!("presets.{}", set) };
+            for (param, _) in values {
+                if !metadata.params.iter().any(|declared| declared.name == param) {
+                    warnings.push(format!(
+                        "Config entry [{}] sets `{}` in {}, which {} does not declare",
+                        defaults.key, param, section, endpoint_name
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut endpoint_names: Vec<&String> = endpoint_data.keys().collect();
+    endpoint_names.sort();
+    for endpoint_name in endpoint_names {
+        let metadata = &endpoint_data[endpoint_name];
+        let config_keys = metadata.config_keys(endpoint_name);
+        let matching: Vec<&EndpointDefaults> =
+            param_defaults.iter().filter(|defaults| defaults.match_rank(&config_keys).is_some()).collect();
+        if matching.len() < 2 {
+            continue;
+        }
+        let used = defaults_index(param_defaults, endpoint_name, metadata).map(|index| &param_defaults[index].key);
+        let keys: Vec<String> = matching.iter().map(|defaults| format!("[{}]", defaults.key)).collect();
+        warnings.push(format!(
+            "Config entries {} all match {}, only [{}] is used",
+            keys.join(", "),
+            endpoint_name,
+            used.map(String::as_str).unwrap_or_default()
+        ));
+    }
+
+    warnings
+}
+
+fn param_strings(params: &HashMap<String, ParamValue>) -> Vec<(String, String)> {
+    let mut param_vec = Vec::new();
+    for (param_name, param_value) in params {
+        let value_str = match param_value {
+            ParamValue::String(s) => s.clone(),
+            ParamValue::Number(n) => n.to_string(),
+            ParamValue::Bool(b) => b.to_string(),
+            // Containers are read back as JSON by `Type::convert_value`
+            ParamValue::Array(_) | ParamValue::Object(_) => serde_json::to_string(param_value).unwrap_or_default(),
+        };
+        param_vec.push((param_name.clone(), value_str));
+    }
+    param_vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParameterMetadata;
+
+    fn metadata(method_id: u32, params: &[&str]) -> EndpointMetadata {
+        EndpointMetadata {
+            service_name: "Users".to_string(),
+            method_id,
+            params: params
+                .iter()
+                .map(|name| ParameterMetadata { name: name.to_string(), ty: Type::String })
+                .collect(),
+            is_stream: false,
+            description: String::new(),
+            returns: Vec::new(),
+            stream_response: None,
+            json_schema: Value::Null,
+        }
+    }
+
+    fn defaults(key: &str, name: &str) -> EndpointDefaults {
+        EndpointDefaults {
+            key: key.to_string(),
+            name: name.to_string(),
+            params: vec![("id".to_string(), key.to_string())],
+            presets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn code_match_wins_over_name_match() {
+        // Sorted by key, the name match comes first
+        let param_defaults = vec![defaults("0_by_name", "GetUser"), defaults("200", "whatever")];
+        let found = find_defaults(&param_defaults, "GetUser", &metadata(200, &["id"])).unwrap();
+        assert_eq!(found.key, "200");
+
+        let param_defaults = vec![defaults("Users.GetUser", ""), defaults("GetUser", "")];
+        let found = find_defaults(&param_defaults, "GetUser", &metadata(200, &["id"])).unwrap();
+        assert_eq!(found.key, "GetUser");
+    }
+
+    #[test]
+    fn native_toml_arrays_and_tables_convert() {
+        let config: crate::parser::Config = toml::from_str(
+            r#"
+            [GetUser]
+            name = "GetUser"
+
+            [GetUser.params]
+            tags = ["a", "b"]
+            filter = { limit = 5, active = true }
+            "#,
+        )
+        .unwrap();
+        let param_defaults = extract_param_defaults(&config.endpoints);
+        let filter = Type::Struct {
+            name: "Filter".to_string(),
+            fields: vec![
+                Field { name: "limit".to_string(), ty: Type::Int },
+                Field { name: "active".to_string(), ty: Type::Boolean },
+            ],
+        };
+        let mut metadata = metadata(200, &[]);
+        metadata.params = vec![
+            ParameterMetadata { name: "tags".to_string(), ty: Type::Vec(Box::new(Type::String)) },
+            ParameterMetadata { name: "filter".to_string(), ty: filter },
+        ];
+        let params = metadata.convert_params(&param_defaults[0].params).unwrap();
+        assert_eq!(params, vec![serde_json::json!({"limit": 5, "active": true}), serde_json::json!(["a", "b"])]);
+    }
+
+    #[test]
+    fn warns_about_shadowed_and_unmatched_tables() {
+        let endpoint_data = HashMap::from([("GetUser".to_string(), metadata(200, &["id"]))]);
+        let param_defaults = vec![defaults("GetUser", ""), defaults("200", ""), defaults("999", "Nope")];
+        let warnings = check_param_defaults(&param_defaults, &endpoint_data);
+        assert_eq!(
+            warnings,
+            vec![
+                "Config entry [999] (name = \"Nope\") matches no endpoint in services.json".to_string(),
+                "Config entries [GetUser], [200] all match GetUser, only [200] is used".to_string(),
+            ]
+        );
+    }
+}
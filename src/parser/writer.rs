@@ -0,0 +1,272 @@
+use crate::generate::Examples;
+use crate::parser::{EndpointMetadata, Type, TypeRegistry};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Writes param values for an endpoint into the config file, as its default
+/// `params` or as `presets.<name>` when `preset` is given. The file is edited
+/// in place so comments, ordering and unrelated entries are kept. Empty
+/// values remove the key. The endpoint's table is found by any of its config
+/// keys, as key or `name`; a new `[<code>]` table is appended when there is none.
+pub fn save_endpoint_params<P: AsRef<Path>>(
+    path: P,
+    endpoint_name: &str,
+    metadata: &EndpointMetadata,
+    preset: Option<&str>,
+    params: &[(String, Type, String)],
+) -> Result<()> {
+    let path = path.as_ref();
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read config file: {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let mut doc: DocumentMut = content.parse().context("Failed to parse config file")?;
+
+    let key = find_endpoint_table(&doc, &metadata.config_keys(endpoint_name))
+        .unwrap_or_else(|| metadata.method_id.to_string());
+    let endpoint = child_table(doc.as_table_mut(), &key)?;
+    if !endpoint.contains_key("name") {
+        endpoint.insert("name", toml_edit::value(endpoint_name));
+    }
+    // `params` is required when the config is loaded, even if only presets are saved
+    child_table(endpoint, "params")?;
+
+    let target = match preset {
+        Some(preset) => {
+            let presets = child_table(endpoint, "presets")?;
+            presets.set_implicit(true);
+            child_table(presets, preset)?
+        }
+        None => child_table(endpoint, "params")?,
+    };
+
+    for (name, ty, raw) in params {
+        if raw.is_empty() {
+            target.remove(name);
+            continue;
+        }
+        let mut value = config_value(ty, raw);
+        // Keep comments attached to a value that is being replaced
+        if let Some(existing) = target.get(name).and_then(Item::as_value) {
+            *value.decor_mut() = existing.decor().clone();
+        }
+        target.insert(name, Item::Value(value));
+    }
+
+    fs::write(path, doc.to_string()).with_context(|| format!("Failed to write config file: {}", path.display()))
+}
+
+/// Writes a starter config for every endpoint in services.json, see
+/// `starter_config`. An existing file is only replaced with `overwrite`.
+pub fn write_starter_config<P: AsRef<Path>>(
+    path: P,
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    registry: &TypeRegistry,
+    overwrite: bool,
+) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() && !overwrite {
+        bail!("Config file {} already exists, pass --force to replace it", path.display());
+    }
+    let content = starter_config(endpoint_data, registry);
+    fs::write(path, content).with_context(|| format!("Failed to write config file: {}", path.display()))
+}
+
+/// One `[<code>]` table per endpoint, ordered by code, with its `name` and an
+/// example value for every declared param. Each value is commented with the
+/// param's type, enum params also with their variants.
+pub fn starter_config(endpoint_data: &HashMap<String, EndpointMetadata>, registry: &TypeRegistry) -> String {
+    let examples = Examples::new(registry);
+    let mut endpoints: Vec<_> = endpoint_data.iter().collect();
+    endpoints.sort_by_key(|(_, metadata)| metadata.method_id);
+
+    let mut doc = DocumentMut::new();
+    for (endpoint_name, metadata) in endpoints {
+        let mut endpoint = Table::new();
+        let summary = metadata.description.lines().next().unwrap_or_default().trim();
+        let heading = match summary {
+            "" => metadata.service_name.clone(),
+            summary => format!("{}: {}", metadata.service_name, summary),
+        };
+        let separator = if doc.is_empty() { "" } else { "\n" };
+        endpoint.decor_mut().set_prefix(format!("{}# {}\n", separator, heading));
+        endpoint.insert("name", toml_edit::value(endpoint_name.as_str()));
+
+        let mut params = Table::new();
+        for param in &metadata.params {
+            let mut value = config_value(&param.ty, &examples.raw(&param.ty));
+            value.decor_mut().set_suffix(format!("  # {}", type_comment(&param.ty, registry)));
+            params.insert(&param.name, Item::Value(value));
+        }
+        endpoint.insert("params", Item::Table(params));
+        doc.insert(&metadata.method_id.to_string(), Item::Table(endpoint));
+    }
+    doc.to_string()
+}
+
+/// The type as shown in the UI, followed by the variants for enums
+fn type_comment(ty: &Type, registry: &TypeRegistry) -> String {
+    let inner = match ty {
+        Type::Optional(inner) => inner.as_ref(),
+        ty => ty,
+    };
+    let variants = match inner {
+        Type::Enum { variants, .. } => Some(variants.as_slice()),
+        Type::EnumRef(name) => registry.enum_variants(name),
+        _ => None,
+    };
+    match variants {
+        Some(variants) => {
+            let names: Vec<&str> = variants.iter().map(|variant| variant.name.as_str()).collect();
+            format!("{}: {}", ty, names.join(", "))
+        }
+        None => ty.to_string(),
+    }
+}
+
+/// Key of the table configuring an endpoint, matched by table key or by `name`.
+/// A match by method code wins like in `defaults_index`.
+fn find_endpoint_table(doc: &DocumentMut, config_keys: &[String]) -> Option<String> {
+    config_keys.iter().find_map(|config_key| {
+        doc.iter()
+            .find(|(key, item)| config_key == key || Some(config_key.as_str()) == item.get("name").and_then(Item::as_str))
+            .map(|(key, _)| key.to_string())
+    })
+}
+
+fn child_table<'a>(parent: &'a mut Table, key: &str) -> Result<&'a mut Table> {
+    parent
+        .entry(key)
+        .or_insert(Item::Table(Table::new()))
+        .as_table_mut()
+        .with_context(|| format!("Config entry `{}` is not a table", key))
+}
+
+/// TOML value for a raw param input. Integers and booleans are written as
+/// such so they parse back into `ParamValue::Number`/`Bool`, everything
+/// else is kept as the string `Type::convert_value` expects.
+fn config_value(ty: &Type, raw: &str) -> Value {
+    let inner = match ty {
+        Type::Optional(inner) => inner.as_ref(),
+        ty => ty,
+    };
+    match inner {
+        Type::Int | Type::BigInt | Type::TimeStampMs => match raw.parse::<i64>() {
+            Ok(number) => number.into(),
+            Err(_) => raw.into(),
+        },
+        Type::Boolean => match raw.parse::<bool>() {
+            Ok(flag) => flag.into(),
+            Err(_) => raw.into(),
+        },
+        _ => raw.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{extract_param_defaults, find_defaults, load_config, EndpointDefaults, Services};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("endpoint_validator_{}_{}.toml", name, std::process::id()))
+    }
+
+    /// `GetUser` (200) taking a param of every kind the config writes differently
+    fn endpoint_data() -> (HashMap<String, EndpointMetadata>, TypeRegistry) {
+        let services: Services = serde_json::from_value(json!({
+            "enums": [{"Enum": {"name": "Role", "variants": [{"name": "Admin", "value": 1, "comment": ""}]}}],
+            "services": [{"name": "Users", "id": 1, "endpoints": [{
+                "name": "GetUser",
+                "code": 200,
+                "parameters": [
+                    {"name": "id", "ty": "BigInt"},
+                    {"name": "active", "ty": "Boolean"},
+                    {"name": "role", "ty": {"EnumRef": "Role"}},
+                    {"name": "tags", "ty": {"Vec": "String"}},
+                    {"name": "filter", "ty": {"Struct": {"name": "Filter", "fields": [{"name": "limit", "ty": "Int"}]}}},
+                    {"name": "note", "ty": {"Optional": "String"}}
+                ],
+                "returns": [],
+                "stream_response": null,
+                "description": "Looks a user up",
+                "json_schema": null
+            }]}]
+        }))
+        .unwrap();
+        let (_, endpoint_data) = services.extract_endpoints();
+        (endpoint_data, TypeRegistry::from_services(&services))
+    }
+
+    fn saved_defaults(path: &Path) -> Vec<EndpointDefaults> {
+        extract_param_defaults(&load_config(path.to_str().unwrap()).unwrap().endpoints)
+    }
+
+    #[test]
+    fn starter_config_loads_and_sends_the_examples() {
+        let (endpoint_data, registry) = endpoint_data();
+        let config = starter_config(&endpoint_data, &registry);
+        assert!(config.starts_with("# Users: Looks a user up\n[200]\n"), "{}", config);
+        assert!(config.contains("role = \"Admin\"  # enum Role: Admin\n"), "{}", config);
+
+        let path = temp_path("starter");
+        fs::write(&path, &config).unwrap();
+        let param_defaults = saved_defaults(&path);
+        fs::remove_file(&path).unwrap();
+
+        let metadata = &endpoint_data["GetUser"];
+        let defaults = find_defaults(&param_defaults, "GetUser", metadata).unwrap();
+        let examples = Examples::new(&registry);
+        let expected: Vec<serde_json::Value> =
+            metadata.sorted_params().iter().map(|param| examples.value(&param.ty)).collect();
+        assert_eq!(metadata.convert_params(&defaults.params).unwrap(), expected);
+    }
+
+    #[test]
+    fn saved_params_load_back_and_keep_comments() {
+        let (endpoint_data, _) = endpoint_data();
+        let metadata = &endpoint_data["GetUser"];
+        let path = temp_path("save");
+        fs::write(&path, "# Users\n[lookup]\nname = \"GetUser\"\n\n[lookup.params]\nid = 1  # the user\nnote = \"old\"\n").unwrap();
+
+        let param = |name: &str, raw: &str| {
+            let ty = metadata.params.iter().find(|param| param.name == name).map(|param| param.ty.clone()).unwrap();
+            (name.to_string(), ty, raw.to_string())
+        };
+        let params = vec![param("id", "42"), param("active", "true"), param("tags", r#"["a","b"]"#), param("note", "")];
+        save_endpoint_params(&path, "GetUser", metadata, None, &params).unwrap();
+        save_endpoint_params(&path, "GetUser", metadata, Some("admin"), &[param("role", "Admin")]).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let param_defaults = saved_defaults(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(content.starts_with("# Users\n[lookup]\n"), "{}", content);
+        assert!(content.contains("id = 42  # the user\n"), "{}", content);
+        assert!(content.contains("[lookup.presets.admin]\nrole = \"Admin\"\n"), "{}", content);
+        assert_eq!(param_defaults.len(), 1);
+        let mut params = param_defaults[0].params.clone();
+        params.sort();
+        let expected = [("active", "true"), ("id", "42"), ("tags", r#"["a","b"]"#)];
+        assert_eq!(params, expected.map(|(name, value)| (name.to_string(), value.to_string())));
+        assert_eq!(param_defaults[0].presets, vec![("admin".to_string(), vec![("role".to_string(), "Admin".to_string())])]);
+    }
+
+    #[test]
+    fn saving_adds_a_table_for_unconfigured_endpoints() {
+        let (endpoint_data, _) = endpoint_data();
+        let metadata = &endpoint_data["GetUser"];
+        let path = temp_path("new_table");
+        let _ = fs::remove_file(&path);
+        let id = vec![("id".to_string(), Type::BigInt, "7".to_string())];
+        save_endpoint_params(&path, "GetUser", metadata, None, &id).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(content, "[200]\nname = \"GetUser\"\n\n[200.params]\nid = 7\n");
+    }
+}
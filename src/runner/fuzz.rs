@@ -0,0 +1,135 @@
+use crate::cli::{ConnectionArgs, FuzzArgs};
+use crate::generate::{Expect, FuzzCase, Fuzzer, Rng};
+use crate::parser::{EndpointMetadata, TypeRegistry};
+use crate::ws::{login_header, response_error_code, response_seq, WsClient};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Longest params or response text printed with a flagged case
+const PREVIEW_LEN: usize = 200;
+
+enum Answer {
+    Response(Value),
+    TimedOut,
+    /// Sending or receiving failed, the server most likely dropped the connection
+    ConnectionLost(anyhow::Error),
+}
+
+/// Generates cases for one endpoint from the seed, sends them one at a time
+/// and flags broken connections, timeouts and invalid input the server
+/// accepted. Returns whether nothing was flagged.
+pub async fn run_fuzz(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    registry: &TypeRegistry,
+    args: &FuzzArgs,
+) -> Result<bool> {
+    if args.cases == 0 {
+        bail!("--cases must be at least 1");
+    }
+    let metadata = endpoint_data
+        .get(&args.endpoint)
+        .with_context(|| format!("Endpoint `{}` is not in services.json", args.endpoint))?;
+    let seed = args.seed.unwrap_or_else(Rng::random_seed);
+    let cases = Fuzzer::new(registry, seed).cases(&metadata.sorted_params(), args.cases);
+    println!("Fuzzing {} ({}) with {} cases, seed {}", args.endpoint, metadata.method_id, cases.len(), seed);
+
+    let wait = Duration::from_millis(args.timeout_ms);
+    let mut client = connect(&args.connection).await?;
+    let mut flagged = 0;
+    for case in &cases {
+        let answer = send_case(&mut client, metadata.method_id, case, wait).await;
+        let Some(problem) = problem(&answer, case.expect, args.timeout_ms) else {
+            continue;
+        };
+        flagged += 1;
+        println!("FLAG {} [{}] {}", case.description, case.expect.label(), problem);
+        println!("     params: {}", preview(&Value::Array(case.params.clone())));
+        // Later cases get a fresh connection rather than a broken or stuck one
+        client = connect(&args.connection)
+            .await
+            .context("Failed to reconnect after a flagged case")?;
+    }
+
+    println!("{} cases sent, {} flagged", cases.len(), flagged);
+    println!("Rerun the same cases with --seed {}", seed);
+    client.close().await.ok();
+    Ok(flagged == 0)
+}
+
+async fn connect(connection: &ConnectionArgs) -> Result<WsClient> {
+    let mut client = WsClient::new(&connection.url, &login_header(&connection.username, &connection.password)).await?;
+    let login = client.recv_raw().await.context("Failed to receive login response")?;
+    if response_error_code(&login).is_some() {
+        return Err(anyhow!("Login failed: {}", login));
+    }
+    Ok(client)
+}
+
+/// Sends one case and waits for the frame answering it, skipping frames of
+/// earlier requests
+async fn send_case(client: &mut WsClient, method_id: u32, case: &FuzzCase, wait: Duration) -> Answer {
+    let seq = match client.send_req(method_id, &case.params).await {
+        Ok(seq) => seq,
+        Err(err) => return Answer::ConnectionLost(err),
+    };
+    let response = timeout(wait, async {
+        loop {
+            let frame = client.recv_raw().await?;
+            if response_seq(&frame) == Some(seq) {
+                return Ok::<_, anyhow::Error>(frame);
+            }
+        }
+    })
+    .await;
+
+    match response {
+        Err(_) => Answer::TimedOut,
+        Ok(Ok(frame)) => Answer::Response(frame),
+        Ok(Err(err)) => Answer::ConnectionLost(err),
+    }
+}
+
+/// Why an answer to a case is flagged: a timeout or broken connection for
+/// any case, and a non-error response to invalid input
+fn problem(answer: &Answer, expect: Expect, timeout_ms: u64) -> Option<String> {
+    match answer {
+        Answer::Response(frame) => match (response_error_code(frame), expect) {
+            (None, Expect::Invalid) => Some(format!("accepted: {}", preview(frame))),
+            _ => None,
+        },
+        Answer::TimedOut => Some(format!("no response within {}ms", timeout_ms)),
+        Answer::ConnectionLost(err) => Some(format!("connection broke: {:#}", err)),
+    }
+}
+
+/// Compact JSON cut to `PREVIEW_LEN` chars
+fn preview(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(PREVIEW_LEN) {
+        Some((index, _)) => format!("{}… ({} chars)", &text[..index], text.chars().count()),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn wrong_answers_are_flagged_for_every_class() {
+        let accepted = || Answer::Response(json!({"type": "Immediate", "seq": 1, "params": {}}));
+        let rejected = || Answer::Response(json!({"type": "Error", "seq": 1, "code": 400}));
+        for expect in [Expect::Valid, Expect::Edge, Expect::Invalid] {
+            assert!(problem(&Answer::TimedOut, expect, 100).is_some(), "{:?}", expect);
+            assert!(problem(&Answer::ConnectionLost(anyhow!("reset")), expect, 100).is_some(), "{:?}", expect);
+            assert!(problem(&rejected(), expect, 100).is_none(), "{:?}", expect);
+        }
+        assert!(problem(&accepted(), Expect::Valid, 100).is_none());
+        assert!(problem(&accepted(), Expect::Edge, 100).is_none());
+        assert!(problem(&accepted(), Expect::Invalid, 100).unwrap().starts_with("accepted: "));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cli::RunArgs;
+use crate::generate::Examples;
+use crate::parser::{find_defaults, EndpointDefaults, EndpointMetadata, TypeRegistry};
+use crate::stats::EndpointStats;
+use crate::ws::{login_header, response_error_code, response_seq, WsClient};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+/// One request to send: an endpoint with one of its param sets
+pub struct Case<'a> {
+    pub endpoint: &'a str,
+    pub metadata: &'a EndpointMetadata,
+    pub preset: String,
+    pub params: Vec<(String, String)>,
+}
+
+pub enum CaseOutcome {
+    Passed,
+    /// Server answered with an error frame
+    Failed(Option<i64>),
+    /// Params could not be converted, the request was never sent
+    Invalid(String),
+    TimedOut,
+}
+
+/// Every configured endpoint once per param set, ordered by method id then preset.
+/// Config entries that match no known endpoint are skipped, params they
+/// leave out are filled in like the TUI does.
+pub fn collect_cases<'a>(
+    endpoint_data: &'a HashMap<String, EndpointMetadata>,
+    param_defaults: &[EndpointDefaults],
+    registry: &TypeRegistry,
+    only: Option<&str>,
+) -> Vec<Case<'a>> {
+    let examples = Examples::new(registry);
+    let mut cases = Vec::new();
+    for (endpoint, metadata) in endpoint_data {
+        if only.is_some_and(|only| only != endpoint) {
+            continue;
+        }
+        let Some(defaults) = find_defaults(param_defaults, endpoint, metadata) else {
+            continue;
+        };
+        for (preset, mut params) in defaults.param_sets() {
+            examples.fill_missing(&metadata.params, &mut params);
+            cases.push(Case { endpoint, metadata, preset, params });
+        }
+    }
+    cases.sort_by(|a, b| (a.metadata.method_id, &a.preset).cmp(&(b.metadata.method_id, &b.preset)));
+    cases
+}
+
+/// Logs in, sends every case and prints one line per result, then the
+/// latency stats of each endpoint. Returns whether all cases passed.
+pub async fn run_headless(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    param_defaults: &[EndpointDefaults],
+    registry: &TypeRegistry,
+    args: &RunArgs,
+) -> Result<bool> {
+    let cases = collect_cases(endpoint_data, param_defaults, registry, args.endpoint.as_deref());
+    if cases.is_empty() {
+        return Err(anyhow!("No configured endpoints to run"));
+    }
+
+    let connection = &args.connection;
+    let mut client = WsClient::new(&connection.url, &login_header(&connection.username, &connection.password)).await?;
+    let login = client.recv_raw().await.context("Failed to receive login response")?;
+    if response_error_code(&login).is_some() {
+        return Err(anyhow!("Login failed: {}", login));
+    }
+
+    let wait = Duration::from_millis(args.timeout_ms);
+    let mut failures = 0;
+    let mut stats = EndpointStats::default();
+    for case in &cases {
+        let started = Instant::now();
+        let outcome = run_case(&mut client, case, wait).await?;
+        let latency = started.elapsed().as_millis();
+        if matches!(outcome, CaseOutcome::Passed | CaseOutcome::Failed(_)) {
+            stats.record_latency(case.endpoint, latency as u64);
+        }
+        let (label, detail) = match &outcome {
+            CaseOutcome::Passed => ("PASS", format!("{}ms", latency)),
+            CaseOutcome::Failed(Some(code)) => ("FAIL", format!("error {} after {}ms", code, latency)),
+            CaseOutcome::Failed(None) => ("FAIL", format!("error after {}ms", latency)),
+            CaseOutcome::Invalid(err) => ("FAIL", err.clone()),
+            CaseOutcome::TimedOut => ("FAIL", format!("no response within {}ms", args.timeout_ms)),
+        };
+        if !matches!(outcome, CaseOutcome::Passed) {
+            failures += 1;
+        }
+        println!("{} {} ({}) [{}] {}", label, case.endpoint, case.metadata.method_id, case.preset, detail);
+    }
+
+    println!("{} passed, {} failed", cases.len() - failures, failures);
+    let report = stats.report();
+    if !report.is_empty() {
+        println!("Latency per endpoint:");
+        for line in report {
+            println!("  {}", line);
+        }
+    }
+    client.close().await?;
+    Ok(failures == 0)
+}
+
+/// Sends one case and waits for the first frame answering it.
+/// Frames belonging to earlier requests (e.g. stream updates) are skipped.
+async fn run_case(client: &mut WsClient, case: &Case<'_>, wait: Duration) -> Result<CaseOutcome> {
+    let params = match case.metadata.convert_params(&case.params) {
+        Ok(params) => params,
+        Err(err) => return Ok(CaseOutcome::Invalid(err.to_string())),
+    };
+    let seq = client.send_req(case.metadata.method_id, params).await?;
+
+    let response = timeout(wait, async {
+        loop {
+            let frame = client.recv_raw().await?;
+            if response_seq(&frame) == Some(seq) {
+                return Ok::<_, anyhow::Error>(frame);
+            }
+        }
+    })
+    .await;
+
+    Ok(match response {
+        Err(_) => CaseOutcome::TimedOut,
+        Ok(frame) => match response_error_code(&frame?) {
+            Some(code) => CaseOutcome::Failed(code),
+            None => CaseOutcome::Passed,
+        },
+    })
+}
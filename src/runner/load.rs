@@ -0,0 +1,457 @@
+use crate::cli::{ConnectionArgs, LoadArgs};
+use crate::generate::Examples;
+use crate::parser::{find_defaults, EndpointDefaults, EndpointMetadata, TypeRegistry};
+use crate::stats::{histogram, summarize, EndpointStats};
+use crate::ws::{login_header, response_error_code, WsClient};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::time::{interval_at, sleep_until, Instant, MissedTickBehavior};
+
+/// Width of the latency histogram bars
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// An endpoint of the load mix with the params sent for it
+pub struct MixEntry {
+    pub endpoint: String,
+    pub method_id: u32,
+    pub params: Vec<Value>,
+    pub weight: u32,
+}
+
+/// Picks mix entries in proportion to their weights, spread evenly rather
+/// than in runs (smooth weighted round robin)
+pub struct WeightedPicker {
+    weights: Vec<i64>,
+    current: Vec<i64>,
+    total: i64,
+}
+
+impl WeightedPicker {
+    pub fn new(weights: impl IntoIterator<Item = u32>) -> Self {
+        let weights: Vec<i64> = weights.into_iter().map(i64::from).collect();
+        Self {
+            current: vec![0; weights.len()],
+            total: weights.iter().sum(),
+            weights,
+        }
+    }
+
+    pub fn next_index(&mut self) -> usize {
+        for (current, weight) in self.current.iter_mut().zip(&self.weights) {
+            *current += weight;
+        }
+        let mut best = 0;
+        for (index, current) in self.current.iter().enumerate() {
+            if *current > self.current[best] {
+                best = index;
+            }
+        }
+        self.current[best] -= self.total;
+        best
+    }
+}
+
+/// Counts and latencies of a load run, shared by all its connections
+#[derive(Default)]
+pub struct LoadResults {
+    pub logged_in: usize,
+    /// Connections that failed to log in or broke off, by error
+    pub connection_errors: BTreeMap<String, usize>,
+    pub sent: usize,
+    pub responses: usize,
+    /// Error responses by code, `None` for errors without a numeric code
+    pub errors: BTreeMap<Option<i64>, usize>,
+    pub timeouts: usize,
+    /// Requests still waiting when their connection broke off
+    pub lost: usize,
+    /// Latency of every response within the timeout, errors included
+    pub latencies: Vec<u64>,
+    pub per_endpoint: EndpointStats,
+}
+
+fn lock(results: &Mutex<LoadResults>) -> MutexGuard<'_, LoadResults> {
+    results.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Resolves `--endpoint name[=weight]` values to endpoints and converts the
+/// params of the chosen param set once up front. Params the config leaves
+/// out are filled in like the TUI does.
+pub fn parse_mix(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    param_defaults: &[EndpointDefaults],
+    registry: &TypeRegistry,
+    args: &LoadArgs,
+) -> Result<Vec<MixEntry>> {
+    let examples = Examples::new(registry);
+    let preset = args.preset.as_deref().unwrap_or("default");
+    let mut mix = Vec::new();
+    for spec in &args.endpoints {
+        let (endpoint, weight) = match spec.rsplit_once('=') {
+            Some((endpoint, weight)) => {
+                let weight = weight.parse().with_context(|| format!("Invalid weight in `{}`", spec))?;
+                (endpoint, weight)
+            }
+            None => (spec.as_str(), 1),
+        };
+        if weight == 0 {
+            bail!("Weight of `{}` must be at least 1", endpoint);
+        }
+        let metadata = endpoint_data
+            .get(endpoint)
+            .with_context(|| format!("Endpoint `{}` is not in services.json", endpoint))?;
+
+        let mut values = match find_defaults(param_defaults, endpoint, metadata) {
+            Some(defaults) => defaults
+                .param_sets()
+                .into_iter()
+                .find(|(name, _)| name == preset)
+                .map(|(_, values)| values)
+                .with_context(|| format!("Endpoint `{}` has no preset `{}`", endpoint, preset))?,
+            None if args.preset.is_none() => Vec::new(),
+            None => bail!("Endpoint `{}` has no config table, so no preset `{}`", endpoint, preset),
+        };
+        examples.fill_missing(&metadata.params, &mut values);
+        let params = metadata
+            .convert_params(&values)
+            .with_context(|| format!("Invalid params for `{}`", endpoint))?;
+
+        mix.push(MixEntry {
+            endpoint: endpoint.to_string(),
+            method_id: metadata.method_id,
+            params,
+            weight,
+        });
+    }
+    Ok(mix)
+}
+
+/// Opens `--connections` logged in connections and sends the endpoint mix
+/// from all of them at `--rate` for `--duration-secs`, then prints the
+/// report. Returns whether every request got a non-error response.
+pub async fn run_load(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    param_defaults: &[EndpointDefaults],
+    registry: &TypeRegistry,
+    args: &LoadArgs,
+) -> Result<bool> {
+    let started = Instant::now();
+    let results = load(endpoint_data, param_defaults, registry, args).await?;
+    print_report(args, &results, started.elapsed());
+    Ok(results.errors.is_empty() && results.timeouts == 0 && results.lost == 0 && results.connection_errors.is_empty())
+}
+
+/// Sends the load of `run_load` and collects its results
+async fn load(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    param_defaults: &[EndpointDefaults],
+    registry: &TypeRegistry,
+    args: &LoadArgs,
+) -> Result<LoadResults> {
+    if args.connections == 0 {
+        bail!("--connections must be at least 1");
+    }
+    if !args.rate.is_finite() || args.rate <= 0.0 {
+        bail!("--rate must be above 0");
+    }
+    let mix = Arc::new(parse_mix(endpoint_data, param_defaults, registry, args)?);
+    let results = Arc::new(Mutex::new(LoadResults::default()));
+
+    let started = Instant::now();
+    let send_until = started + Duration::from_secs(args.duration_secs);
+    let timeout = Duration::from_millis(args.timeout_ms);
+    let period = send_period(args.connections, args.rate)?;
+    let mut tasks = Vec::new();
+    for index in 0..args.connections {
+        let offset = period.mul_f64(index as f64 / args.connections as f64);
+        let pacing = Pacing { first_send: started + offset, period, send_until, timeout };
+        tasks.push(tokio::spawn(run_connection(
+            args.connection.clone(),
+            Arc::clone(&mix),
+            Arc::clone(&results),
+            pacing,
+        )));
+    }
+    for task in tasks {
+        if let Err(err) = task.await? {
+            *lock(&results).connection_errors.entry(format!("{:#}", err)).or_default() += 1;
+        }
+    }
+
+    let results = std::mem::take(&mut *lock(&results));
+    if results.logged_in == 0 {
+        let error = results.connection_errors.keys().next().cloned().unwrap_or_default();
+        return Err(anyhow!("No connection could log in: {}", error));
+    }
+    Ok(results)
+}
+
+/// Time between the requests of one connection, each sending its share of
+/// the rate. Rates too high to pace are sent as fast as possible.
+fn send_period(connections: usize, rate: f64) -> Result<Duration> {
+    let period = Duration::try_from_secs_f64(connections as f64 / rate)
+        .map_err(|_| anyhow!("--rate {} is too low for {} connections", rate, connections))?;
+    // `interval_at` panics on a zero period
+    Ok(period.max(Duration::from_nanos(1)))
+}
+
+/// When one connection sends and how long it waits for the last responses
+struct Pacing {
+    first_send: Instant,
+    period: Duration,
+    send_until: Instant,
+    timeout: Duration,
+}
+
+/// Logs one connection in, then sends a request every `period` until
+/// `send_until` while reading responses, and waits up to `timeout` for
+/// the last ones
+async fn run_connection(
+    connection: ConnectionArgs,
+    mix: Arc<Vec<MixEntry>>,
+    results: Arc<Mutex<LoadResults>>,
+    pacing: Pacing,
+) -> Result<()> {
+    let mut client = WsClient::new(&connection.url, &login_header(&connection.username, &connection.password)).await?;
+    let login = client.recv_raw().await.context("Failed to receive login response")?;
+    if response_error_code(&login).is_some() {
+        bail!("Login failed: {}", login);
+    }
+    lock(&results).logged_in += 1;
+
+    let (mut sender, mut receiver) = client.split();
+    let mut picker = WeightedPicker::new(mix.iter().map(|entry| entry.weight));
+    // Requests waiting for their response: seq -> (sent at, mix index)
+    let mut pending: HashMap<u32, (Instant, usize)> = HashMap::new();
+    let mut ticker = interval_at(pacing.first_send, pacing.period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let drain_until = pacing.send_until + pacing.timeout;
+
+    loop {
+        let sending = Instant::now() < pacing.send_until;
+        if !sending && pending.is_empty() {
+            break;
+        }
+        tokio::select! {
+            _ = ticker.tick(), if sending => {
+                let index = picker.next_index();
+                let entry = &mix[index];
+                let sent_at = Instant::now();
+                match sender.send_req(entry.method_id, &entry.params).await {
+                    Ok(seq) => {
+                        pending.insert(seq, (sent_at, index));
+                        lock(&results).sent += 1;
+                    }
+                    Err(err) => {
+                        lock(&results).lost += pending.len();
+                        return Err(err);
+                    }
+                }
+            }
+            _ = sleep_until(pacing.send_until), if sending => {}
+            frame = receiver.recv_raw() => {
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        lock(&results).lost += pending.len();
+                        return Err(err);
+                    }
+                };
+                // Only the immediate response or error answers a request, stream frames don't
+                let Some(seq) = frame.get("seq").and_then(Value::as_u64) else {
+                    continue;
+                };
+                let Some((sent_at, index)) = pending.remove(&(seq as u32)) else {
+                    continue;
+                };
+                let latency = sent_at.elapsed();
+                let mut results = lock(&results);
+                if latency > pacing.timeout {
+                    results.timeouts += 1;
+                    continue;
+                }
+                let ms = latency.as_millis() as u64;
+                results.responses += 1;
+                results.latencies.push(ms);
+                results.per_endpoint.record_latency(&mix[index].endpoint, ms);
+                if let Some(code) = response_error_code(&frame) {
+                    *results.errors.entry(code).or_default() += 1;
+                }
+            }
+            _ = sleep_until(drain_until) => break,
+        }
+    }
+
+    lock(&results).timeouts += pending.len();
+    sender.close().await.ok();
+    Ok(())
+}
+
+fn print_report(args: &LoadArgs, results: &LoadResults, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "{} connections ({} logged in), target {} req/s for {}s",
+        args.connections, results.logged_in, args.rate, args.duration_secs
+    );
+    println!(
+        "Sent {} requests in {:.1}s ({:.1}/s), {} responses ({:.1}/s)",
+        results.sent,
+        seconds,
+        results.sent as f64 / seconds,
+        results.responses,
+        results.responses as f64 / seconds
+    );
+
+    let error_count: usize = results.errors.values().sum();
+    println!("Errors: {}, timeouts: {}, lost: {}", error_count, results.timeouts, results.lost);
+    for (code, count) in &results.errors {
+        match code {
+            Some(code) => println!("  error {}: {}", code, count),
+            None => println!("  error without code: {}", count),
+        }
+    }
+    if !results.connection_errors.is_empty() {
+        println!("Connection errors:");
+        for (error, count) in &results.connection_errors {
+            println!("  {}x {}", count, error);
+        }
+    }
+
+    if let Some(summary) = summarize(&results.latencies) {
+        println!("Latency: {}", summary);
+        for line in histogram(&results.latencies, HISTOGRAM_WIDTH) {
+            println!("  {}", line);
+        }
+    }
+    if args.endpoints.len() > 1 {
+        println!("Latency per endpoint:");
+        for line in results.per_endpoint.report() {
+            println!("  {}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParameterMetadata, Type, TypeRegistry};
+    use crate::runner::serve_mock;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    fn endpoint_data() -> HashMap<String, EndpointMetadata> {
+        let metadata = |method_id, params| EndpointMetadata {
+            service_name: "Users".to_string(),
+            method_id,
+            params,
+            is_stream: false,
+            description: String::new(),
+            returns: vec![ParameterMetadata { name: "name".to_string(), ty: Type::String }],
+            stream_response: None,
+            json_schema: Value::Null,
+        };
+        let id = ParameterMetadata { name: "id".to_string(), ty: Type::Int };
+        HashMap::from([
+            ("GetUser".to_string(), metadata(200, vec![id])),
+            ("DeleteUser".to_string(), metadata(201, Vec::new())),
+        ])
+    }
+
+    fn param_defaults() -> Vec<EndpointDefaults> {
+        vec![EndpointDefaults {
+            key: "GetUser".to_string(),
+            name: String::new(),
+            params: vec![("id".to_string(), "7".to_string())],
+            presets: vec![("admin".to_string(), vec![("id".to_string(), "1".to_string())])],
+        }]
+    }
+
+    fn load_args(url: &str, endpoints: &[&str]) -> LoadArgs {
+        LoadArgs {
+            connection: ConnectionArgs { url: url.to_string(), username: "user".to_string(), password: "pass".to_string() },
+            endpoints: endpoints.iter().map(|endpoint| endpoint.to_string()).collect(),
+            preset: None,
+            connections: 1,
+            rate: 20.0,
+            duration_secs: 1,
+            timeout_ms: 2000,
+        }
+    }
+
+    #[test]
+    fn picker_spreads_picks_by_weight() {
+        let mut picker = WeightedPicker::new([3, 1]);
+        let picks: Vec<usize> = (0..8).map(|_| picker.next_index()).collect();
+        assert_eq!(picks, vec![0, 0, 1, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn send_period_stays_above_zero() {
+        assert_eq!(send_period(4, 100.0).unwrap(), Duration::from_millis(40));
+        assert_eq!(send_period(1, 1e10).unwrap(), Duration::from_nanos(1));
+        assert_eq!(send_period(1, 1e300).unwrap(), Duration::from_nanos(1));
+        assert!(send_period(10, 1e-300).is_err());
+    }
+
+    #[test]
+    fn mix_takes_weights_and_config_params() {
+        let mix = parse_mix(&endpoint_data(), &param_defaults(), &TypeRegistry::default(), &load_args("", &["GetUser=3", "DeleteUser"])).unwrap();
+        let entries: Vec<(&str, u32, u32)> =
+            mix.iter().map(|entry| (entry.endpoint.as_str(), entry.method_id, entry.weight)).collect();
+        assert_eq!(entries, vec![("GetUser", 200, 3), ("DeleteUser", 201, 1)]);
+        assert_eq!(mix[0].params, vec![json!(7)]);
+        assert!(mix[1].params.is_empty());
+
+        let mut args = load_args("", &["GetUser"]);
+        args.preset = Some("admin".to_string());
+        let mix = parse_mix(&endpoint_data(), &param_defaults(), &TypeRegistry::default(), &args).unwrap();
+        assert_eq!(mix[0].params, vec![json!(1)]);
+    }
+
+    #[test]
+    fn mix_fills_in_params_the_config_leaves_out() {
+        let mut defaults = param_defaults();
+        defaults[0].params.clear();
+        let mix = parse_mix(&endpoint_data(), &defaults, &TypeRegistry::default(), &load_args("", &["GetUser"])).unwrap();
+        assert_eq!(mix[0].params, vec![json!(1)]);
+
+        let mix = parse_mix(&endpoint_data(), &[], &TypeRegistry::default(), &load_args("", &["GetUser"])).unwrap();
+        assert_eq!(mix[0].params, vec![json!(1)]);
+    }
+
+    #[test]
+    fn mix_rejects_bad_specs() {
+        for spec in ["GetUser=0", "GetUser=x", "Nope"] {
+            assert!(parse_mix(&endpoint_data(), &param_defaults(), &TypeRegistry::default(), &load_args("", &[spec])).is_err(), "{}", spec);
+        }
+        let mut args = load_args("", &["GetUser"]);
+        args.preset = Some("missing".to_string());
+        assert!(parse_mix(&endpoint_data(), &param_defaults(), &TypeRegistry::default(), &args).is_err());
+    }
+
+    #[tokio::test]
+    async fn load_against_the_mock_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // The mock doesn't know DeleteUser, so every other request is answered with an error
+        let mut mock_endpoints = endpoint_data();
+        mock_endpoints.remove("DeleteUser");
+        let mock = tokio::spawn(async move {
+            serve_mock(listener, &mock_endpoints, &TypeRegistry::default(), Duration::from_secs(1)).await
+        });
+
+        let results = load(&endpoint_data(), &param_defaults(), &TypeRegistry::default(), &load_args(&url, &["GetUser", "DeleteUser"]))
+            .await
+            .unwrap();
+        mock.abort();
+
+        assert_eq!(results.logged_in, 1);
+        assert!((10..=25).contains(&results.sent), "sent {}", results.sent);
+        assert_eq!(results.responses, results.sent);
+        assert_eq!(results.errors, BTreeMap::from([(Some(404), results.sent / 2)]));
+        assert_eq!((results.timeouts, results.lost), (0, 0));
+        assert!(results.connection_errors.is_empty());
+    }
+}
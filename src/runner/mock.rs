@@ -0,0 +1,167 @@
+use crate::cli::MockArgs;
+use crate::generate::Examples;
+use crate::parser::{EndpointMetadata, TypeRegistry};
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, info, warn};
+
+/// Error code sent for methods that are not in services.json
+const UNKNOWN_METHOD_CODE: i64 = 404;
+
+/// Example answers of one endpoint, built once at startup
+struct MockEndpoint {
+    response: Value,
+    /// Payload of every stream frame, `None` for endpoints without a stream
+    stream_frame: Option<Value>,
+}
+
+/// A stream started on a connection, answered with a frame every interval
+struct ActiveStream {
+    method: u32,
+    original_seq: Value,
+    stream_seq: u64,
+}
+
+/// Serves example values for every endpoint in services.json: the endpoint's
+/// `returns` as the response, and `stream_response` frames every
+/// `--stream-interval-ms` for stream endpoints. Runs until it is stopped.
+pub async fn run_mock(
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    registry: &TypeRegistry,
+    args: &MockArgs,
+) -> Result<()> {
+    let listener = TcpListener::bind(&args.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", args.listen))?;
+    println!("Mock server listening on ws://{}", listener.local_addr()?);
+    serve_mock(listener, endpoint_data, registry, Duration::from_millis(args.stream_interval_ms)).await
+}
+
+/// Answers the connections of `listener` like `run_mock`
+pub async fn serve_mock(
+    listener: TcpListener,
+    endpoint_data: &HashMap<String, EndpointMetadata>,
+    registry: &TypeRegistry,
+    stream_interval: Duration,
+) -> Result<()> {
+    let examples = Examples::new(registry);
+    let endpoints: HashMap<u32, MockEndpoint> = endpoint_data
+        .values()
+        .map(|metadata| {
+            let response: Map<String, Value> = metadata
+                .returns
+                .iter()
+                .map(|field| (field.name.clone(), examples.value(&field.ty)))
+                .collect();
+            let endpoint = MockEndpoint {
+                response: Value::Object(response),
+                stream_frame: metadata.stream_response.as_ref().map(|ty| examples.value(ty)),
+            };
+            (metadata.method_id, endpoint)
+        })
+        .collect();
+    let endpoints = Arc::new(endpoints);
+    let stream_interval = stream_interval.max(Duration::from_millis(1));
+    loop {
+        let (tcp, peer) = listener.accept().await.context("Failed to accept connection")?;
+        let endpoints = Arc::clone(&endpoints);
+        tokio::spawn(async move {
+            info!(%peer, "Mock connection opened");
+            match serve_connection(tcp, &endpoints, stream_interval).await {
+                Ok(()) => info!(%peer, "Mock connection closed"),
+                Err(err) => warn!(%peer, "Mock connection failed: {:#}", err),
+            }
+        });
+    }
+}
+
+/// Accepts the login from the protocol header, then answers requests and
+/// sends the frames of the streams they started until the client goes away
+async fn serve_connection(tcp: TcpStream, endpoints: &HashMap<u32, MockEndpoint>, stream_interval: Duration) -> Result<()> {
+    let mut ws = tokio_tungstenite::accept_hdr_async(tcp, accept_login)
+        .await
+        .context("Handshake failed")?;
+    send(&mut ws, json!({ "type": "Immediate", "method": 0, "seq": 0, "params": {} })).await?;
+
+    let mut streams: Vec<ActiveStream> = Vec::new();
+    let mut ticker = interval(stream_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            message = ws.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(err).context("Failed to receive message"),
+                };
+                let request: Value = match serde_json::from_str(&text) {
+                    Ok(request) => request,
+                    Err(err) => {
+                        warn!("Ignoring malformed request: {}", err);
+                        continue;
+                    }
+                };
+                let method = request.get("method").and_then(Value::as_u64).unwrap_or_default() as u32;
+                let seq = request.get("seq").cloned().unwrap_or(Value::Null);
+                debug!(method, %seq, "Mock request");
+
+                let Some(endpoint) = endpoints.get(&method) else {
+                    let error = json!({
+                        "type": "Error",
+                        "method": method,
+                        "seq": seq,
+                        "code": UNKNOWN_METHOD_CODE,
+                        "params": format!("Unknown method {}", method),
+                    });
+                    send(&mut ws, error).await?;
+                    continue;
+                };
+                let response = json!({ "type": "Immediate", "method": method, "seq": seq, "params": endpoint.response });
+                send(&mut ws, response).await?;
+                if endpoint.stream_frame.is_some() {
+                    streams.push(ActiveStream { method, original_seq: seq, stream_seq: 0 });
+                }
+            }
+            _ = ticker.tick(), if !streams.is_empty() => {
+                for stream in &mut streams {
+                    stream.stream_seq += 1;
+                    let data = endpoints.get(&stream.method).and_then(|endpoint| endpoint.stream_frame.clone());
+                    let frame = json!({
+                        "type": "Stream",
+                        "method": stream.method,
+                        "original_seq": stream.original_seq,
+                        "stream_seq": stream.stream_seq,
+                        "stream_code": 0,
+                        "data": data,
+                    });
+                    send(&mut ws, frame).await?;
+                }
+            }
+        }
+    }
+}
+
+/// Any credentials log in; the protocol header is echoed back since clients
+/// expect the server to pick it
+// The error type is fixed by tungstenite's handshake callback
+#[allow(clippy::result_large_err)]
+fn accept_login(request: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+    if let Some(protocol) = request.headers().get("Sec-WebSocket-Protocol") {
+        response.headers_mut().insert("Sec-WebSocket-Protocol", protocol.clone());
+    }
+    Ok(response)
+}
+
+async fn send(ws: &mut WebSocketStream<TcpStream>, frame: Value) -> Result<()> {
+    ws.send(Message::Text(frame.to_string())).await.context("Failed to send frame")
+}
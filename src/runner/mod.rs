@@ -0,0 +1,9 @@
+mod fuzz;
+mod headless;
+mod load;
+mod mock;
+
+pub use fuzz::*;
+pub use headless::*;
+pub use load::*;
+pub use mock::*;
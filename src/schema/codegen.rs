@@ -0,0 +1,405 @@
+use crate::parser::{EndpointSchema, EnumVariant, Field, Services, Type, TypeRegistry};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// A Rust module with typed bindings for every endpoint: `<Service><Endpoint>Req`
+/// and `<Service><Endpoint>Resp` structs, enums from `EnumData`, the named
+/// structs used in params and returns, and an async method per endpoint on
+/// `TypedClient`, a wrapper around the `WsClient` of `ws_module`
+pub fn rust_client(services: &Services, ws_module: &str) -> String {
+    let registry = TypeRegistry::from_services(services);
+    let mut gen = Codegen::new(&registry);
+    for data in &services.enums {
+        let definition = data.definition();
+        gen.enum_item(&definition.name, &definition.variants);
+    }
+    let endpoints: Vec<(&str, &EndpointSchema)> = services
+        .services
+        .iter()
+        .flat_map(|service| service.endpoints.iter().map(move |endpoint| (service.name.as_str(), endpoint)))
+        .collect();
+    for (service, endpoint) in &endpoints {
+        gen.endpoint_types(service, endpoint);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "//! Typed client generated by `endpoint_validator codegen` from services.json. Do not edit.");
+    out.push_str("#![allow(dead_code, clippy::all)]\n\n");
+    let _ = writeln!(out, "use {}::{{response_error_code, response_seq, WsClient}};", ws_module);
+    out.push_str(HEADER);
+    out.push_str(&gen.items);
+    out.push_str(CLIENT);
+    for (service, endpoint) in &endpoints {
+        let name = endpoint_ident(service, &endpoint.name);
+        doc_comment(&mut out, "    ", &endpoint.description);
+        let _ = writeln!(
+            out,
+            "    pub async fn {}(&mut self, req: {}Req) -> Result<{}Resp> {{\n        self.call({}, req.to_params()?).await\n    }}\n",
+            field_ident(&format!("{}_{}", service, endpoint.name)),
+            name,
+            name,
+            endpoint.code
+        );
+    }
+    out.truncate(out.trim_end().len());
+    out.push_str("\n}\n");
+    out
+}
+
+const HEADER: &str = r#"use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+"#;
+
+const CLIENT: &str = r#"/// Typed requests over a logged in `WsClient`
+pub struct TypedClient {
+    pub client: WsClient,
+}
+
+impl TypedClient {
+    pub fn new(client: WsClient) -> Self {
+        Self { client }
+    }
+
+    /// Sends a request and waits for its response, skipping frames of earlier requests
+    async fn call<Resp: DeserializeOwned>(&mut self, method: u32, params: Vec<Value>) -> Result<Resp> {
+        let seq = self.client.send_req(method, params).await?;
+        loop {
+            let frame = self.client.recv_raw().await?;
+            if response_seq(&frame) != Some(seq) {
+                continue;
+            }
+            if let Some(code) = response_error_code(&frame) {
+                bail!("Method {} failed with error {:?}: {}", method, code, frame);
+            }
+            let params = match frame.get("params") {
+                None | Some(Value::Null) => Value::Object(Default::default()),
+                Some(params) => params.clone(),
+            };
+            return serde_json::from_value(params).with_context(|| format!("Unexpected response to method {}", method));
+        }
+    }
+
+    /// Payload of the next stream frame of any request, with the seq of that request
+    pub async fn next_stream<T: DeserializeOwned>(&mut self) -> Result<(u32, T)> {
+        loop {
+            let frame = self.client.recv_raw().await?;
+            let Some(seq) = frame.get("original_seq").and_then(Value::as_u64) else {
+                continue;
+            };
+            let data = frame.get("data").cloned().unwrap_or(Value::Null);
+            return Ok((seq as u32, serde_json::from_value(data).context("Unexpected stream frame")?));
+        }
+    }
+
+"#;
+
+struct Codegen<'a> {
+    registry: &'a TypeRegistry,
+    items: String,
+    /// Names of the structs and enums written so far
+    defined: HashSet<String>,
+    /// Identifiers of the inline structs by name, one per distinct field list
+    structs: HashMap<String, Vec<(Vec<Field>, String)>>,
+}
+
+impl<'a> Codegen<'a> {
+    fn new(registry: &'a TypeRegistry) -> Self {
+        Self { registry, items: String::new(), defined: HashSet::new(), structs: HashMap::new() }
+    }
+
+    fn endpoint_types(&mut self, service: &str, endpoint: &EndpointSchema) {
+        let name = endpoint_ident(service, &endpoint.name);
+        let label = format!("{}.{}", service, endpoint.name);
+        for field in endpoint.parameters.iter().chain(&endpoint.returns) {
+            self.nested_items(&field.ty, &label);
+        }
+        if let Some(stream) = &endpoint.stream_response {
+            self.nested_items(stream, &label);
+        }
+
+        // Params go over the wire as a list sorted by name
+        let mut params: Vec<&Field> = endpoint.parameters.iter().collect();
+        params.sort_by(|a, b| a.name.cmp(&b.name));
+        doc_comment(&mut self.items, "", &format!("Params of {}", label));
+        self.struct_item(&format!("{}Req", name), params.iter().copied());
+        let _ = writeln!(self.items, "impl {}Req {{", name);
+        let _ = writeln!(self.items, "    /// Params as sent: a list sorted by name");
+        let _ = writeln!(self.items, "    pub fn to_params(&self) -> serde_json::Result<Vec<Value>> {{");
+        let values: Vec<String> = params
+            .iter()
+            .map(|param| format!("serde_json::to_value(&self.{})?", field_ident(&param.name)))
+            .collect();
+        let _ = writeln!(self.items, "        Ok(vec![{}])", values.join(", "));
+        let _ = writeln!(self.items, "    }}\n}}\n");
+
+        doc_comment(&mut self.items, "", &format!("Response of {}", label));
+        self.struct_item(&format!("{}Resp", name), endpoint.returns.iter());
+        if let Some(stream) = &endpoint.stream_response {
+            doc_comment(&mut self.items, "", &format!("Payload of the stream frames of {}", label));
+            let _ = writeln!(self.items, "pub type {}Stream = {};\n", name, self.rust_type(stream));
+        }
+    }
+
+    /// Structs and enums defined inline in `ty`, each written once
+    fn nested_items(&mut self, ty: &Type, label: &str) {
+        match ty {
+            Type::Optional(inner) | Type::Vec(inner) => self.nested_items(inner, label),
+            Type::Struct { name, fields } | Type::DataTable { name, fields } => {
+                let Some(ident) = self.new_struct_ident(name, fields) else {
+                    return;
+                };
+                for field in fields {
+                    self.nested_items(&field.ty, label);
+                }
+                if ident != type_ident(name) {
+                    doc_comment(&mut self.items, "", &format!("`{}` as used in {}, with other fields than `{}`", name, label, type_ident(name)));
+                }
+                self.struct_item(&ident, fields.iter());
+            }
+            Type::Enum { name, variants } => self.enum_item(name, variants),
+            _ => {}
+        }
+    }
+
+    /// Identifier for a struct not seen before, `None` if these fields were.
+    /// Structs sharing a name but not their fields are numbered in the order
+    /// they are found, so refs (which `TypeRegistry` resolves to the first
+    /// definition) get the unnumbered one.
+    fn new_struct_ident(&mut self, name: &str, fields: &[Field]) -> Option<String> {
+        let known = self.structs.entry(name.to_string()).or_default();
+        if known.iter().any(|(known_fields, _)| known_fields == fields) {
+            return None;
+        }
+        let base = type_ident(name);
+        let mut ident = base.clone();
+        let mut number = 1;
+        while !self.defined.insert(ident.clone()) {
+            number += 1;
+            ident = format!("{}{}", base, number);
+        }
+        known.push((fields.to_vec(), ident.clone()));
+        Some(ident)
+    }
+
+    fn struct_ident(&self, name: &str, fields: &[Field]) -> String {
+        self.structs
+            .get(name)
+            .and_then(|known| known.iter().find(|(known_fields, _)| known_fields == fields))
+            .map_or_else(|| type_ident(name), |(_, ident)| ident.clone())
+    }
+
+    fn struct_item<'f>(&mut self, name: &str, fields: impl Iterator<Item = &'f Field>) {
+        let _ = writeln!(self.items, "#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]");
+        let mut fields = fields.peekable();
+        if fields.peek().is_none() {
+            let _ = writeln!(self.items, "pub struct {} {{}}\n", name);
+            return;
+        }
+        let mut body = String::new();
+        for field in fields {
+            let ident = field_ident(&field.name);
+            if ident.trim_start_matches("r#") != field.name {
+                let _ = writeln!(body, "    #[serde(rename = {:?})]", field.name);
+            }
+            // Structs holding themselves need the indirection to have a size
+            let ty = if self.holds_inline(&field.ty, name, &mut HashSet::new()) {
+                self.boxed_type(&field.ty)
+            } else {
+                self.rust_type(&field.ty)
+            };
+            let _ = writeln!(body, "    pub {}: {},", ident, ty);
+        }
+        let _ = writeln!(self.items, "pub struct {} {{\n{}}}\n", name, body);
+    }
+
+    /// Whether a value of `ty` holds a `target` struct without a `Vec` in between
+    fn holds_inline(&self, ty: &Type, target: &str, visited: &mut HashSet<String>) -> bool {
+        let fields = match ty {
+            Type::Optional(inner) => return self.holds_inline(inner, target, visited),
+            Type::Struct { fields, .. } => fields.as_slice(),
+            Type::StructRef(name) => match self.registry.struct_fields(name) {
+                Some(fields) => fields,
+                None => return false,
+            },
+            _ => return false,
+        };
+        let ident = self.rust_type(ty);
+        ident == target || (visited.insert(ident) && fields.iter().any(|field| self.holds_inline(&field.ty, target, visited)))
+    }
+
+    fn boxed_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::Optional(inner) => format!("Option<{}>", self.boxed_type(inner)),
+            _ => format!("Box<{}>", self.rust_type(ty)),
+        }
+    }
+
+    fn enum_item(&mut self, name: &str, variants: &[EnumVariant]) {
+        let name = type_ident(name);
+        if !self.defined.insert(name.clone()) {
+            return;
+        }
+        let _ = writeln!(self.items, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]");
+        let _ = writeln!(self.items, "pub enum {} {{", name);
+        for variant in variants {
+            doc_comment(&mut self.items, "    ", &variant.comment);
+            let ident = type_ident(&variant.name);
+            if ident != variant.name {
+                let _ = writeln!(self.items, "    #[serde(rename = {:?})]", variant.name);
+            }
+            let _ = writeln!(self.items, "    {},", ident);
+        }
+        let _ = writeln!(self.items, "}}\n");
+    }
+
+    fn rust_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::TimeStampMs | Type::BigInt => "i64".to_string(),
+            Type::Int => "i32".to_string(),
+            Type::Numeric => "f64".to_string(),
+            Type::Boolean => "bool".to_string(),
+            Type::Date
+            | Type::String
+            | Type::Bytea
+            | Type::UUID
+            | Type::Inet
+            | Type::BlockchainDecimal
+            | Type::BlockchainAddress
+            | Type::BlockchainTransactionHash => "String".to_string(),
+            Type::Object => "Value".to_string(),
+            Type::Unit => "()".to_string(),
+            Type::Struct { name, fields } => self.struct_ident(name, fields),
+            Type::DataTable { name, fields } => format!("Vec<{}>", self.struct_ident(name, fields)),
+            Type::StructRef(name) => match self.registry.struct_fields(name) {
+                Some(fields) => self.struct_ident(name, fields),
+                None => type_ident(name),
+            },
+            Type::Enum { name, .. } | Type::EnumRef(name) => type_ident(name),
+            Type::Vec(inner) => format!("Vec<{}>", self.rust_type(inner)),
+            Type::Optional(inner) => format!("Option<{}>", self.rust_type(inner)),
+        }
+    }
+}
+
+fn doc_comment(out: &mut String, indent: &str, text: &str) {
+    for line in text.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()) {
+        let _ = writeln!(out, "{}/// {}", indent, line.trim_start());
+    }
+}
+
+/// Words of a name in any case style: `getUser`, `get_user` and `GET_USER`
+/// all give `get` and `user`
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            previous_lower = false;
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Prefix of an endpoint's types: service and endpoint name, since endpoint
+/// names are only unique within their service
+fn endpoint_ident(service: &str, endpoint: &str) -> String {
+    format!("{}{}", type_ident(service), type_ident(endpoint))
+}
+
+/// Type or variant identifier; names already in PascalCase are kept as they are
+fn type_ident(name: &str) -> String {
+    let already_pascal =
+        name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().all(|c| c.is_ascii_alphanumeric());
+    let ident = if already_pascal && name.chars().any(|c| c.is_ascii_lowercase()) {
+        name.to_string()
+    } else {
+        pascal_case(name)
+    };
+    match ident.chars().next() {
+        None => "Unnamed".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("V{}", ident),
+        _ if KEYWORDS.contains(&ident.as_str()) => format!("{}_", ident),
+        _ => ident,
+    }
+}
+
+/// Field or method identifier in snake_case, keywords as raw identifiers
+fn field_ident(name: &str) -> String {
+    let ident = words(name).join("_");
+    match ident.as_str() {
+        "" => "unnamed".to_string(),
+        "self" | "crate" | "super" => format!("{}_", ident),
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{}", ident),
+        _ if ident.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+// Compiling the checked in output is the test that generated code builds
+#[cfg(test)]
+#[path = "testdata/client.rs"]
+mod generated_client;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_client_is_up_to_date() {
+        let services: Services = serde_json::from_str(include_str!("testdata/services.json")).unwrap();
+        let generated = rust_client(&services, "crate::ws");
+        assert!(
+            generated == include_str!("testdata/client.rs"),
+            "Regenerate with `cargo run -- --services-path src/schema/testdata/services.json codegen --output src/schema/testdata/client.rs`"
+        );
+    }
+
+    #[test]
+    fn generated_client_boxes_recursion_and_keeps_names_apart() {
+        let services: Services = serde_json::from_str(include_str!("testdata/services.json")).unwrap();
+        let generated = rust_client(&services, "crate::ws");
+        for line in [
+            "    pub manager: Option<Box<Profile>>,\n",
+            "    pub reports: Vec<Profile>,\n",
+            "    pub root: Box<Branch>,\n",
+            "    pub address: Address2,\n",
+            "    pub async fn user_get_profile(&mut self, req: UserGetProfileReq) -> Result<UserGetProfileResp> {\n",
+            "    pub async fn admin_get_profile(&mut self, req: AdminGetProfileReq) -> Result<AdminGetProfileResp> {\n",
+        ] {
+            assert!(generated.contains(line), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use crate::parser::{EndpointSchema, EnumVariant, Field, Services, Type, TypeRegistry};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Existing clients may stop working
+    Breaking,
+    /// Existing clients keep working
+    Additive,
+}
+
+pub struct SchemaChange {
+    pub kind: ChangeKind,
+    pub message: String,
+}
+
+/// Which way values of a type travel, which decides what breaks clients
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// Params: clients send them, so the server may only accept more
+    Input,
+    /// Returns and stream frames: clients read them, so the server may only send more
+    Output,
+}
+
+/// Changes from `old` to `new` in services, endpoints (matched by name, with
+/// codes compared), params, returns, stream types and enums
+pub fn diff_services(old: &Services, new: &Services) -> Vec<SchemaChange> {
+    let old_types = TypeRegistry::from_services(old);
+    let new_types = TypeRegistry::from_services(new);
+    let listed_enums = old
+        .enums
+        .iter()
+        .map(|data| data.definition().name.as_str())
+        .filter(|name| new.enums.iter().any(|data| data.definition().name == *name))
+        .collect();
+    let mut diff = SchemaDiff { old_types: &old_types, new_types: &new_types, listed_enums, changes: Vec::new() };
+    diff.services(old, new);
+    diff.endpoints(old, new);
+    diff.enums(old, new);
+    diff.changes
+}
+
+/// Prints breaking changes first, then additive ones, then the counts
+pub fn print_changes(changes: &[SchemaChange]) {
+    if changes.is_empty() {
+        println!("No changes");
+        return;
+    }
+    for (kind, heading) in [(ChangeKind::Breaking, "Breaking changes:"), (ChangeKind::Additive, "Additive changes:")] {
+        let messages: Vec<&str> = changes
+            .iter()
+            .filter(|change| change.kind == kind)
+            .map(|change| change.message.as_str())
+            .collect();
+        if !messages.is_empty() {
+            println!("{}", heading);
+            for message in messages {
+                println!("  {}", message);
+            }
+        }
+    }
+    let breaking = changes.iter().filter(|change| change.kind == ChangeKind::Breaking).count();
+    println!("{} breaking, {} additive", breaking, changes.len() - breaking);
+}
+
+struct SchemaDiff<'a> {
+    old_types: &'a TypeRegistry,
+    new_types: &'a TypeRegistry,
+    /// Enums in both top-level lists, which `enums` compares
+    listed_enums: HashSet<&'a str>,
+    changes: Vec<SchemaChange>,
+}
+
+impl<'a> SchemaDiff<'a> {
+    fn breaking(&mut self, message: String) {
+        self.changes.push(SchemaChange { kind: ChangeKind::Breaking, message });
+    }
+
+    fn additive(&mut self, message: String) {
+        self.changes.push(SchemaChange { kind: ChangeKind::Additive, message });
+    }
+
+    fn services(&mut self, old: &Services, new: &Services) {
+        for service in &old.services {
+            match new.services.iter().find(|candidate| candidate.name == service.name) {
+                None => self.breaking(format!("Removed service {}", service.name)),
+                Some(renewed) if renewed.id != service.id => self.breaking(format!(
+                    "Service {} changed id from {} to {}",
+                    service.name, service.id, renewed.id
+                )),
+                Some(_) => {}
+            }
+        }
+        for service in &new.services {
+            if !old.services.iter().any(|candidate| candidate.name == service.name) {
+                self.additive(format!("Added service {}", service.name));
+            }
+        }
+    }
+
+    fn endpoints(&mut self, old: &Services, new: &Services) {
+        let old_endpoints = all_endpoints(old);
+        let new_endpoints = all_endpoints(new);
+        for endpoint in &old_endpoints {
+            match new_endpoints.iter().find(|candidate| candidate.name == endpoint.name) {
+                Some(renewed) => {
+                    if renewed.code != endpoint.code {
+                        self.breaking(format!(
+                            "{}: code changed from {} to {}",
+                            endpoint.name, endpoint.code, renewed.code
+                        ));
+                    }
+                    self.endpoint(endpoint, renewed);
+                }
+                None => match new_endpoints.iter().find(|candidate| candidate.code == endpoint.code) {
+                    Some(reused) => self.breaking(format!(
+                        "Code {} reassigned from {} to {}",
+                        endpoint.code, endpoint.name, reused.name
+                    )),
+                    None => self.breaking(format!("Removed endpoint {} ({})", endpoint.name, endpoint.code)),
+                },
+            }
+        }
+        for endpoint in &new_endpoints {
+            let known = old_endpoints
+                .iter()
+                .any(|candidate| candidate.name == endpoint.name || candidate.code == endpoint.code);
+            if !known {
+                self.additive(format!("Added endpoint {} ({})", endpoint.name, endpoint.code));
+            }
+        }
+    }
+
+    fn endpoint(&mut self, old: &EndpointSchema, new: &EndpointSchema) {
+        let name = &new.name;
+        for param in &old.parameters {
+            let path = format!("{} params.{}", name, param.name);
+            match new.parameters.iter().find(|candidate| candidate.name == param.name) {
+                Some(renewed) => self.types(&path, &param.ty, &renewed.ty, Direction::Input, &mut HashSet::new()),
+                None => self.breaking(format!("{}: removed", path)),
+            }
+        }
+        for param in &new.parameters {
+            if old.parameters.iter().any(|candidate| candidate.name == param.name) {
+                continue;
+            }
+            let path = format!("{} params.{}", name, param.name);
+            // Params are sent as a list sorted by name
+            let shifts = old.parameters.iter().any(|existing| existing.name > param.name);
+            if !matches!(param.ty, Type::Optional(_)) {
+                self.breaking(format!("{}: added as a required param", path));
+            } else if shifts {
+                self.breaking(format!("{}: added before existing params, shifting their positions", path));
+            } else {
+                self.additive(format!("{}: added as an optional param", path));
+            }
+        }
+
+        let path = format!("{} returns", name);
+        self.fields(&path, &old.returns, &new.returns, Direction::Output, &mut HashSet::new());
+
+        let path = format!("{} stream", name);
+        match (&old.stream_response, &new.stream_response) {
+            (None, None) => {}
+            (None, Some(_)) => self.additive(format!("{}: added", path)),
+            (Some(_), None) => self.breaking(format!("{}: removed", path)),
+            (Some(old_ty), Some(new_ty)) => self.types(&path, old_ty, new_ty, Direction::Output, &mut HashSet::new()),
+        }
+    }
+
+    /// Fields of a struct, table row or the returns, `path` leading to them
+    fn fields(&mut self, path: &str, old: &[Field], new: &[Field], direction: Direction, visited: &mut HashSet<String>) {
+        let join = |field: &Field| format!("{}.{}", path, field.name);
+        for field in old {
+            match new.iter().find(|candidate| candidate.name == field.name) {
+                Some(renewed) => self.types(&join(field), &field.ty, &renewed.ty, direction, visited),
+                None => self.breaking(format!("{}: removed", join(field))),
+            }
+        }
+        for field in new {
+            if old.iter().any(|candidate| candidate.name == field.name) {
+                continue;
+            }
+            if direction == Direction::Input && !matches!(field.ty, Type::Optional(_)) {
+                self.breaking(format!("{}: added as a required field", join(field)));
+            } else {
+                self.additive(format!("{}: added", join(field)));
+            }
+        }
+    }
+
+    fn types(&mut self, path: &str, old: &Type, new: &Type, direction: Direction, visited: &mut HashSet<String>) {
+        match (old, new) {
+            (Type::Optional(old_inner), Type::Optional(new_inner)) => {
+                return self.types(path, old_inner, new_inner, direction, visited);
+            }
+            (Type::Optional(old_inner), _) => {
+                match direction {
+                    Direction::Input => self.breaking(format!("{}: no longer optional", path)),
+                    Direction::Output => self.additive(format!("{}: no longer null", path)),
+                }
+                return self.types(path, old_inner, new, direction, visited);
+            }
+            (_, Type::Optional(new_inner)) => {
+                match direction {
+                    Direction::Input => self.additive(format!("{}: now optional", path)),
+                    Direction::Output => self.breaking(format!("{}: may now be null", path)),
+                }
+                return self.types(path, old, new_inner, direction, visited);
+            }
+            (Type::Vec(old_item), Type::Vec(new_item)) => {
+                return self.types(&format!("{}[]", path), old_item, new_item, direction, visited);
+            }
+            _ => {}
+        }
+
+        // Listed enums are compared once in `enums`, inline ones where they are used
+        if let (Some(old_name), Some(new_name)) = (enum_name(old), enum_name(new)) {
+            if old_name == new_name && self.listed_enums.contains(old_name) {
+                return;
+            }
+        }
+
+        let old_struct = struct_of(old, self.old_types);
+        let new_struct = struct_of(new, self.new_types);
+        if let (Some((old_name, old_fields, old_table)), Some((_, new_fields, new_table))) = (old_struct, new_struct) {
+            if old_table == new_table {
+                // Recursive structs are followed once per path
+                if visited.insert(old_name.to_string()) {
+                    let path = if old_table { format!("{}[]", path) } else { path.to_string() };
+                    self.fields(&path, old_fields, new_fields, direction, visited);
+                    visited.remove(old_name);
+                }
+                return;
+            }
+        }
+
+        let old_variants = variants_of(old, self.old_types);
+        let new_variants = variants_of(new, self.new_types);
+        if let (Some(old_variants), Some(new_variants)) = (old_variants, new_variants) {
+            self.variants(path, old_variants, new_variants);
+            return;
+        }
+
+        if old != new {
+            self.breaking(format!("{}: type changed from {} to {}", path, old, new));
+        }
+    }
+
+    fn variants(&mut self, path: &str, old: &[EnumVariant], new: &[EnumVariant]) {
+        for variant in old {
+            match new.iter().find(|candidate| candidate.name == variant.name) {
+                None => self.breaking(format!("{}: removed variant {}", path, variant.name)),
+                Some(renewed) if renewed.value != variant.value => self.breaking(format!(
+                    "{}: variant {} changed value from {} to {}",
+                    path, variant.name, variant.value, renewed.value
+                )),
+                Some(_) => {}
+            }
+        }
+        for variant in new {
+            if !old.iter().any(|candidate| candidate.name == variant.name) {
+                self.additive(format!("{}: added variant {}", path, variant.name));
+            }
+        }
+    }
+
+    fn enums(&mut self, old: &Services, new: &Services) {
+        for data in &old.enums {
+            let definition = data.definition();
+            let path = format!("enum {}", definition.name);
+            match new.enums.iter().map(|data| data.definition()).find(|candidate| candidate.name == definition.name) {
+                Some(renewed) => self.variants(&path, &definition.variants, &renewed.variants),
+                None => self.breaking(format!("Removed {}", path)),
+            }
+        }
+        for data in &new.enums {
+            let definition = data.definition();
+            if !old.enums.iter().any(|candidate| candidate.definition().name == definition.name) {
+                self.additive(format!("Added enum {}", definition.name));
+            }
+        }
+    }
+}
+
+fn all_endpoints(services: &Services) -> Vec<&EndpointSchema> {
+    services.services.iter().flat_map(|service| &service.endpoints).collect()
+}
+
+/// Name and fields of a struct or table type, with refs looked up, and
+/// whether it is a table
+fn struct_of<'t>(ty: &'t Type, registry: &'t TypeRegistry) -> Option<(&'t str, &'t [Field], bool)> {
+    match ty {
+        Type::Struct { name, fields } => Some((name, fields, false)),
+        Type::DataTable { name, fields } => Some((name, fields, true)),
+        Type::StructRef(name) => registry.struct_fields(name).map(|fields| (name.as_str(), fields, false)),
+        _ => None,
+    }
+}
+
+fn enum_name(ty: &Type) -> Option<&str> {
+    match ty {
+        Type::Enum { name, .. } | Type::EnumRef(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// Variants of an enum type, with refs looked up
+fn variants_of<'t>(ty: &'t Type, registry: &'t TypeRegistry) -> Option<&'t [EnumVariant]> {
+    match ty {
+        Type::Enum { variants, .. } => Some(variants),
+        Type::EnumRef(name) => registry.enum_variants(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// One `Users` service with a single `GetUser` endpoint
+    fn services(params: Value, returns: Value, enums: Value) -> Services {
+        serde_json::from_value(json!({
+            "enums": enums,
+            "services": [{"name": "Users", "id": 1, "endpoints": [{
+                "name": "GetUser",
+                "code": 200,
+                "parameters": params,
+                "returns": returns,
+                "stream_response": null,
+                "description": "",
+                "json_schema": null
+            }]}]
+        }))
+        .unwrap()
+    }
+
+    fn role(variants: &[(&str, i64)]) -> Value {
+        let variants: Vec<Value> =
+            variants.iter().map(|(name, value)| json!({"name": name, "value": value, "comment": ""})).collect();
+        json!([{"Enum": {"name": "Role", "variants": variants}}])
+    }
+
+    fn changes(old: &Services, new: &Services) -> Vec<(ChangeKind, String)> {
+        diff_services(old, new).into_iter().map(|change| (change.kind, change.message)).collect()
+    }
+
+    #[test]
+    fn unchanged_schema_has_no_changes() {
+        let schema = || {
+            services(json!([{"name": "id", "ty": "Int"}]), json!([{"name": "name", "ty": "String"}]), role(&[("Admin", 1)]))
+        };
+        assert!(changes(&schema(), &schema()).is_empty());
+    }
+
+    #[test]
+    fn params_becoming_required_break() {
+        let old = services(json!([{"name": "id", "ty": {"Optional": "Int"}}]), json!([]), json!([]));
+        let new = services(json!([{"name": "id", "ty": "Int"}]), json!([]), json!([]));
+        assert_eq!(changes(&old, &new), vec![(ChangeKind::Breaking, "GetUser params.id: no longer optional".to_string())]);
+        assert_eq!(changes(&new, &old), vec![(ChangeKind::Additive, "GetUser params.id: now optional".to_string())]);
+    }
+
+    #[test]
+    fn added_params_break_unless_optional_and_last() {
+        let old = services(json!([{"name": "id", "ty": "Int"}]), json!([]), json!([]));
+        let new = services(
+            json!([
+                {"name": "id", "ty": "Int"},
+                {"name": "limit", "ty": {"Optional": "Int"}},
+                {"name": "after", "ty": {"Optional": "Int"}},
+                {"name": "name", "ty": "String"}
+            ]),
+            json!([]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                (ChangeKind::Additive, "GetUser params.limit: added as an optional param".to_string()),
+                (
+                    ChangeKind::Breaking,
+                    "GetUser params.after: added before existing params, shifting their positions".to_string()
+                ),
+                (ChangeKind::Breaking, "GetUser params.name: added as a required param".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_and_nullable_returns_break() {
+        let old = services(
+            json!([]),
+            json!([{"name": "name", "ty": "String"}, {"name": "age", "ty": "Int"}]),
+            json!([]),
+        );
+        let new = services(
+            json!([]),
+            json!([{"name": "age", "ty": {"Optional": "Int"}}, {"name": "email", "ty": "String"}]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                (ChangeKind::Breaking, "GetUser returns.name: removed".to_string()),
+                (ChangeKind::Breaking, "GetUser returns.age: may now be null".to_string()),
+                (ChangeKind::Additive, "GetUser returns.email: added".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_and_renumbered_variants_break() {
+        let old = services(json!([{"name": "role", "ty": {"EnumRef": "Role"}}]), json!([]), role(&[("Admin", 1), ("User", 2)]));
+        let new = services(json!([{"name": "role", "ty": {"EnumRef": "Role"}}]), json!([]), role(&[("Admin", 3), ("Guest", 4)]));
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                (ChangeKind::Breaking, "enum Role: variant Admin changed value from 1 to 3".to_string()),
+                (ChangeKind::Breaking, "enum Role: removed variant User".to_string()),
+                (ChangeKind::Additive, "enum Role: added variant Guest".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_enums_are_compared_where_they_are_used() {
+        let status = |variants: Value| {
+            services(
+                json!([{"name": "status", "ty": {"EnumRef": "Status"}}]),
+                json!([{"name": "status", "ty": {"Enum": {"name": "Status", "variants": variants}}}]),
+                json!([]),
+            )
+        };
+        let old = status(json!([{"name": "Active", "value": 1, "comment": ""}, {"name": "Banned", "value": 2, "comment": ""}]));
+        let new = status(json!([{"name": "Active", "value": 1, "comment": ""}]));
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                (ChangeKind::Breaking, "GetUser params.status: removed variant Banned".to_string()),
+                (ChangeKind::Breaking, "GetUser returns.status: removed variant Banned".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_types_in_recursive_structs_are_found() {
+        let node = |value_ty: &str| {
+            json!([{"name": "root", "ty": {"Struct": {"name": "Node", "fields": [
+                {"name": "value", "ty": value_ty},
+                {"name": "children", "ty": {"Vec": {"StructRef": "Node"}}}
+            ]}}}])
+        };
+        let old = services(json!([]), node("Int"), json!([]));
+        let new = services(json!([]), node("String"), json!([]));
+        assert_eq!(
+            changes(&old, &new),
+            vec![(ChangeKind::Breaking, "GetUser returns.root.value: type changed from int to string".to_string())]
+        );
+    }
+
+    #[test]
+    fn reassigned_codes_and_removed_endpoints_break() {
+        let old = services(json!([]), json!([]), json!([]));
+        let mut new = services(json!([]), json!([]), json!([]));
+        new.services[0].endpoints[0].name = "FindUser".to_string();
+        assert_eq!(
+            changes(&old, &new),
+            vec![(ChangeKind::Breaking, "Code 200 reassigned from GetUser to FindUser".to_string())]
+        );
+        new.services[0].endpoints[0].code = 201;
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                (ChangeKind::Breaking, "Removed endpoint GetUser (200)".to_string()),
+                (ChangeKind::Additive, "Added endpoint FindUser (201)".to_string()),
+            ]
+        );
+    }
+}
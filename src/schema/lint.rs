@@ -0,0 +1,450 @@
+use crate::parser::{EnumVariant, Field, Services, Type, TypeRegistry};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Makes endpoints unreachable or their types unusable
+    Error,
+    /// Style and documentation
+    Warning,
+}
+
+pub struct LintIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Structural problems in services.json: duplicate codes and names, dangling
+/// `StructRef`/`EnumRef`, enums with duplicate values, `json_schema` that
+/// disagrees with `parameters`, missing descriptions and names that don't
+/// follow the conventions (PascalCase services, endpoints, structs and enums,
+/// snake_case params and fields, PascalCase or UPPER_SNAKE_CASE variants)
+pub fn lint_services(services: &Services) -> Vec<LintIssue> {
+    let registry = TypeRegistry::from_services(services);
+    let mut lint = Lint { registry: &registry, issues: Vec::new(), structs: BTreeMap::new(), enums_checked: HashSet::new() };
+    lint.services(services);
+    lint.endpoints(services);
+    for data in &services.enums {
+        let definition = data.definition();
+        lint.enum_definition(&format!("enum {}", definition.name), &definition.name, &definition.variants);
+    }
+    lint.issues
+}
+
+/// Prints errors first, then warnings, then the counts
+pub fn print_issues(issues: &[LintIssue]) {
+    if issues.is_empty() {
+        println!("No problems found");
+        return;
+    }
+    for (severity, label) in [(Severity::Error, "error"), (Severity::Warning, "warning")] {
+        for issue in issues.iter().filter(|issue| issue.severity == severity) {
+            println!("{}: {}", label, issue.message);
+        }
+    }
+    let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+    println!("{} errors, {} warnings", errors, issues.len() - errors);
+}
+
+struct Lint<'a> {
+    registry: &'a TypeRegistry,
+    issues: Vec<LintIssue>,
+    /// Fields of every struct seen so far by name, with where it was first defined
+    structs: BTreeMap<String, (String, Vec<Field>)>,
+    /// Inline enums are checked once, however often they are used
+    enums_checked: HashSet<String>,
+}
+
+impl<'a> Lint<'a> {
+    fn error(&mut self, message: String) {
+        self.issues.push(LintIssue { severity: Severity::Error, message });
+    }
+
+    fn warning(&mut self, message: String) {
+        self.issues.push(LintIssue { severity: Severity::Warning, message });
+    }
+
+    fn services(&mut self, services: &Services) {
+        let mut names: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut ids: BTreeMap<u32, Vec<&str>> = BTreeMap::new();
+        for service in &services.services {
+            *names.entry(&service.name).or_default() += 1;
+            ids.entry(service.id).or_default().push(&service.name);
+            if !is_pascal_case(&service.name) {
+                self.warning(format!("Service {} is not PascalCase", service.name));
+            }
+        }
+        for (name, count) in names.into_iter().filter(|(_, count)| *count > 1) {
+            self.error(format!("Service name {} is declared {} times", name, count));
+        }
+        for (id, names) in ids.into_iter().filter(|(_, names)| names.len() > 1) {
+            self.error(format!("Service id {} is used by {}", id, names.join(", ")));
+        }
+
+        let mut enum_names: BTreeMap<&str, usize> = BTreeMap::new();
+        for data in &services.enums {
+            *enum_names.entry(&data.definition().name).or_default() += 1;
+        }
+        for (name, count) in enum_names.into_iter().filter(|(_, count)| *count > 1) {
+            self.error(format!("Enum {} is declared {} times", name, count));
+        }
+    }
+
+    fn endpoints(&mut self, services: &Services) {
+        let mut codes: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        let mut names: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for service in &services.services {
+            for endpoint in &service.endpoints {
+                codes.entry(endpoint.code).or_default().push(format!("{}.{}", service.name, endpoint.name));
+                names.entry(&endpoint.name).or_default().push(format!("{} ({})", service.name, endpoint.code));
+            }
+        }
+        for (code, endpoints) in codes.into_iter().filter(|(_, endpoints)| endpoints.len() > 1) {
+            self.error(format!("Code {} is used by {}", code, endpoints.join(", ")));
+        }
+        for (name, declared) in names.into_iter().filter(|(_, declared)| declared.len() > 1) {
+            self.error(format!(
+                "Endpoint name {} is declared in {}; only the first is reachable by name",
+                name,
+                declared.join(", ")
+            ));
+        }
+
+        for service in &services.services {
+            for endpoint in &service.endpoints {
+                let name = &endpoint.name;
+                if !is_pascal_case(name) {
+                    self.warning(format!("Endpoint {} is not PascalCase", name));
+                }
+                if endpoint.description.trim().is_empty() {
+                    self.warning(format!("Endpoint {} has no description", name));
+                }
+                self.fields(&format!("{} params", name), &endpoint.parameters);
+                self.fields(&format!("{} returns", name), &endpoint.returns);
+                if let Some(stream) = &endpoint.stream_response {
+                    self.walk(&format!("{} stream", name), stream);
+                }
+                self.json_schema(name, &endpoint.parameters, &endpoint.json_schema);
+            }
+        }
+    }
+
+    /// Params, returns or struct fields: duplicates, naming and their types
+    fn fields(&mut self, path: &str, fields: &[Field]) {
+        let mut seen = HashSet::new();
+        for field in fields {
+            let field_path = format!("{}.{}", path, field.name);
+            if !seen.insert(&field.name) {
+                self.error(format!("{}: declared more than once", field_path));
+            }
+            if !is_snake_case(&field.name) {
+                self.warning(format!("{}: not snake_case", field_path));
+            }
+            self.walk(&field_path, &field.ty);
+        }
+    }
+
+    fn walk(&mut self, path: &str, ty: &Type) {
+        match ty {
+            Type::Optional(inner) => self.walk(path, inner),
+            Type::Vec(inner) => self.walk(&format!("{}[]", path), inner),
+            Type::Struct { name, fields } | Type::DataTable { name, fields } => {
+                let path = if matches!(ty, Type::DataTable { .. }) { format!("{}[]", path) } else { path.to_string() };
+                match self.structs.get(name) {
+                    // Already checked where it was first defined
+                    Some((_, known)) if known == fields => return,
+                    Some((first, _)) => {
+                        let first = first.clone();
+                        self.error(format!("{}: struct {} differs from its definition at {}", path, name, first));
+                        return;
+                    }
+                    None => {
+                        self.structs.insert(name.clone(), (path.clone(), fields.clone()));
+                    }
+                }
+                if !is_pascal_case(name) {
+                    self.warning(format!("{}: struct {} is not PascalCase", path, name));
+                }
+                self.fields(&path, fields);
+            }
+            Type::StructRef(name) if self.registry.struct_fields(name).is_none() => {
+                self.error(format!("{}: StructRef {} matches no struct", path, name));
+            }
+            Type::EnumRef(name) if self.registry.enum_variants(name).is_none() => {
+                self.error(format!("{}: EnumRef {} matches no enum", path, name));
+            }
+            Type::Enum { name, variants } if self.enums_checked.insert(name.clone()) => {
+                self.enum_definition(&format!("{}: enum {}", path, name), name, variants);
+            }
+            _ => {}
+        }
+    }
+
+    fn enum_definition(&mut self, path: &str, name: &str, variants: &[EnumVariant]) {
+        if !is_pascal_case(name) {
+            self.warning(format!("{}: not PascalCase", path));
+        }
+        if variants.is_empty() {
+            self.warning(format!("{}: has no variants", path));
+        }
+        let mut values: BTreeMap<i64, Vec<&str>> = BTreeMap::new();
+        let mut names = HashSet::new();
+        for variant in variants {
+            values.entry(variant.value).or_default().push(&variant.name);
+            if !names.insert(&variant.name) {
+                self.error(format!("{}: variant {} is declared more than once", path, variant.name));
+            }
+            if !is_pascal_case(&variant.name) && !is_upper_snake_case(&variant.name) {
+                self.warning(format!("{}: variant {} is neither PascalCase nor UPPER_SNAKE_CASE", path, variant.name));
+            }
+        }
+        for (value, names) in values.into_iter().filter(|(_, names)| names.len() > 1) {
+            self.error(format!("{}: variants {} share the value {}", path, names.join(", "), value));
+        }
+    }
+
+    /// Compares a JSON Schema object of the params with the `parameters`
+    /// list. Schemas without `properties` are not checked, and without
+    /// `required` only the property names are.
+    fn json_schema(&mut self, endpoint: &str, params: &[Field], schema: &Value) {
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return;
+        };
+        let required: Option<HashSet<&str>> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect());
+
+        for param in params {
+            let path = format!("{} params.{}", endpoint, param.name);
+            if !properties.contains_key(&param.name) {
+                self.error(format!("{}: missing from json_schema", path));
+                continue;
+            }
+            let schema_types = schema_types(&properties[&param.name]);
+            let accepted = json_types(&param.ty);
+            if schema_types.iter().any(|ty| *ty != "null" && !accepted.contains(ty)) {
+                self.error(format!("{}: {}, but {} in json_schema", path, param.ty, schema_types.join(" or ")));
+            }
+            let Some(required) = &required else {
+                continue;
+            };
+            let optional = matches!(param.ty, Type::Optional(_));
+            if optional && required.contains(param.name.as_str()) {
+                self.error(format!("{}: optional, but required in json_schema", path));
+            } else if !optional && !required.contains(param.name.as_str()) {
+                self.error(format!("{}: required, but optional in json_schema", path));
+            }
+        }
+        for property in properties.keys() {
+            if !params.iter().any(|param| param.name == *property) {
+                self.error(format!("{} json_schema: property {} is not a param", endpoint, property));
+            }
+        }
+    }
+}
+
+/// The `type` of a json_schema property, given as one name or a list of them
+fn schema_types(property: &Value) -> Vec<&str> {
+    match property.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// json_schema types a param of `ty` may be declared as, after `convert_value`
+fn json_types(ty: &Type) -> &'static [&'static str] {
+    match ty {
+        Type::Optional(inner) => json_types(inner),
+        Type::Int | Type::BigInt | Type::TimeStampMs => &["integer", "number"],
+        Type::Numeric => &["number"],
+        Type::BlockchainDecimal => &["string", "number"],
+        Type::Boolean => &["boolean"],
+        Type::Date | Type::String | Type::Bytea | Type::UUID | Type::Inet => &["string"],
+        Type::BlockchainAddress | Type::BlockchainTransactionHash => &["string"],
+        Type::Enum { .. } | Type::EnumRef(_) => &["string", "integer"],
+        Type::Struct { .. } | Type::StructRef(_) | Type::Object => &["object"],
+        Type::Vec(_) | Type::DataTable { .. } => &["array"],
+        Type::Unit => &["null"],
+    }
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn is_upper_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn endpoint(name: &str, code: u32, parameters: Value, json_schema: Value) -> Value {
+        json!({
+            "name": name,
+            "code": code,
+            "parameters": parameters,
+            "returns": [],
+            "stream_response": null,
+            "description": "Does things",
+            "json_schema": json_schema
+        })
+    }
+
+    fn issues(endpoints: Vec<Value>, enums: Value) -> Vec<(Severity, String)> {
+        let services: Services = serde_json::from_value(json!({
+            "enums": enums,
+            "services": [{"name": "Users", "id": 1, "endpoints": endpoints}]
+        }))
+        .unwrap();
+        lint_services(&services).into_iter().map(|issue| (issue.severity, issue.message)).collect()
+    }
+
+    fn errors(issues: &[(Severity, String)]) -> Vec<&str> {
+        issues
+            .iter()
+            .filter(|(severity, _)| *severity == Severity::Error)
+            .map(|(_, message)| message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn clean_schema_has_no_issues() {
+        let params = json!([{"name": "user_id", "ty": "Int"}, {"name": "role", "ty": {"EnumRef": "Role"}}]);
+        let enums = json!([{"Enum": {"name": "Role", "variants": [{"name": "Admin", "value": 1, "comment": ""}]}}]);
+        assert!(issues(vec![endpoint("GetUser", 200, params, Value::Null)], enums).is_empty());
+    }
+
+    #[test]
+    fn duplicate_codes_and_names_are_errors() {
+        let issues = issues(
+            vec![
+                endpoint("GetUser", 200, json!([]), Value::Null),
+                endpoint("GetUser", 201, json!([]), Value::Null),
+                endpoint("ListUsers", 201, json!([]), Value::Null),
+            ],
+            json!([]),
+        );
+        assert_eq!(
+            errors(&issues),
+            vec![
+                "Code 201 is used by Users.GetUser, Users.ListUsers",
+                "Endpoint name GetUser is declared in Users (200), Users (201); only the first is reachable by name",
+            ]
+        );
+    }
+
+    #[test]
+    fn dangling_refs_are_errors() {
+        let params = json!([
+            {"name": "owner", "ty": {"Optional": {"StructRef": "Owner"}}},
+            {"name": "roles", "ty": {"Vec": {"EnumRef": "Role"}}}
+        ]);
+        let issues = issues(vec![endpoint("GetUser", 200, params, Value::Null)], json!([]));
+        assert_eq!(
+            errors(&issues),
+            vec![
+                "GetUser params.owner: StructRef Owner matches no struct",
+                "GetUser params.roles[]: EnumRef Role matches no enum",
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_values_and_structs_must_agree() {
+        let user = |fields: Value| json!({"Struct": {"name": "User", "fields": fields}});
+        let params = json!([
+            {"name": "user", "ty": user(json!([{"name": "id", "ty": "Int"}]))},
+            {"name": "other", "ty": user(json!([{"name": "id", "ty": "String"}]))}
+        ]);
+        let enums = json!([{"Enum": {"name": "Role", "variants": [
+            {"name": "Admin", "value": 1, "comment": ""},
+            {"name": "User", "value": 1, "comment": ""}
+        ]}}]);
+        let issues = issues(vec![endpoint("GetUser", 200, params, Value::Null)], enums);
+        assert_eq!(
+            errors(&issues),
+            vec![
+                "GetUser params.other: struct User differs from its definition at GetUser params.user",
+                "enum Role: variants Admin, User share the value 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_schema_must_match_the_params() {
+        let params = json!([
+            {"name": "id", "ty": "Int"},
+            {"name": "limit", "ty": {"Optional": "Int"}},
+            {"name": "name", "ty": "String"}
+        ]);
+        let schema = json!({
+            "properties": {"id": {}, "limit": {}, "extra": {}},
+            "required": ["limit"]
+        });
+        let issues = issues(vec![endpoint("GetUser", 200, params, schema)], json!([]));
+        assert_eq!(
+            errors(&issues),
+            vec![
+                "GetUser params.id: required, but optional in json_schema",
+                "GetUser params.limit: optional, but required in json_schema",
+                "GetUser params.name: missing from json_schema",
+                "GetUser json_schema: property extra is not a param",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_schema_types_must_match_the_param_types() {
+        let params = json!([
+            {"name": "id", "ty": "BigInt"},
+            {"name": "limit", "ty": {"Optional": "Int"}},
+            {"name": "name", "ty": "String"},
+            {"name": "tags", "ty": {"Vec": "String"}}
+        ]);
+        let schema = json!({
+            "properties": {
+                "id": {"type": "integer"},
+                "limit": {"type": ["integer", "null"]},
+                "name": {"type": "integer"},
+                "tags": {"type": ["string", "null"]}
+            }
+        });
+        let issues = issues(vec![endpoint("GetUser", 200, params, schema)], json!([]));
+        assert_eq!(
+            errors(&issues),
+            vec![
+                "GetUser params.name: string, but integer in json_schema",
+                "GetUser params.tags: list<string>, but string or null in json_schema",
+            ]
+        );
+    }
+
+    #[test]
+    fn naming_and_descriptions_are_warnings() {
+        let mut get_user = endpoint("get_user", 200, json!([{"name": "userId", "ty": "Int"}]), Value::Null);
+        get_user["description"] = json!("");
+        let issues = issues(vec![get_user], json!([]));
+        assert!(errors(&issues).is_empty());
+        let warnings: Vec<&str> = issues.iter().map(|(_, message)| message.as_str()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "Endpoint get_user is not PascalCase",
+                "Endpoint get_user has no description",
+                "get_user params.userId: not snake_case",
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+mod codegen;
+mod diff;
+mod lint;
+
+pub use codegen::*;
+pub use diff::*;
+pub use lint::*;
@@ -0,0 +1,171 @@
+//! Typed client generated by `endpoint_validator codegen` from services.json. Do not edit.
+#![allow(dead_code, clippy::all)]
+
+use crate::ws::{response_error_code, response_seq, WsClient};
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// Can do anything
+    Admin,
+    #[serde(rename = "READ_ONLY")]
+    ReadOnly,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Address {
+    pub street: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub role: Role,
+    pub address: Address,
+    pub manager: Option<Box<Profile>>,
+    pub reports: Vec<Profile>,
+}
+
+/// Params of User.GetProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserGetProfileReq {
+    pub r#type: Option<String>,
+    pub user_id: i64,
+}
+
+impl UserGetProfileReq {
+    /// Params as sent: a list sorted by name
+    pub fn to_params(&self) -> serde_json::Result<Vec<Value>> {
+        Ok(vec![serde_json::to_value(&self.r#type)?, serde_json::to_value(&self.user_id)?])
+    }
+}
+
+/// Response of User.GetProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserGetProfileResp {
+    pub profile: Profile,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProfileEvent {
+    pub at: i64,
+    pub profile: Profile,
+}
+
+/// Params of User.WatchProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserWatchProfileReq {
+    pub user_id: i64,
+}
+
+impl UserWatchProfileReq {
+    /// Params as sent: a list sorted by name
+    pub fn to_params(&self) -> serde_json::Result<Vec<Value>> {
+        Ok(vec![serde_json::to_value(&self.user_id)?])
+    }
+}
+
+/// Response of User.WatchProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserWatchProfileResp {}
+
+/// Payload of the stream frames of User.WatchProfile
+pub type UserWatchProfileStream = Vec<ProfileEvent>;
+
+/// `Address` as used in Admin.GetProfile, with other fields than `Address`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Address2 {
+    pub street: String,
+    pub city: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Branch {
+    pub label: String,
+    pub subtree: Option<Box<Tree>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tree {
+    pub root: Box<Branch>,
+}
+
+/// Params of Admin.GetProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdminGetProfileReq {
+    pub user_id: i64,
+}
+
+impl AdminGetProfileReq {
+    /// Params as sent: a list sorted by name
+    pub fn to_params(&self) -> serde_json::Result<Vec<Value>> {
+        Ok(vec![serde_json::to_value(&self.user_id)?])
+    }
+}
+
+/// Response of Admin.GetProfile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdminGetProfileResp {
+    pub address: Address2,
+    pub tree: Tree,
+}
+
+/// Typed requests over a logged in `WsClient`
+pub struct TypedClient {
+    pub client: WsClient,
+}
+
+impl TypedClient {
+    pub fn new(client: WsClient) -> Self {
+        Self { client }
+    }
+
+    /// Sends a request and waits for its response, skipping frames of earlier requests
+    async fn call<Resp: DeserializeOwned>(&mut self, method: u32, params: Vec<Value>) -> Result<Resp> {
+        let seq = self.client.send_req(method, params).await?;
+        loop {
+            let frame = self.client.recv_raw().await?;
+            if response_seq(&frame) != Some(seq) {
+                continue;
+            }
+            if let Some(code) = response_error_code(&frame) {
+                bail!("Method {} failed with error {:?}: {}", method, code, frame);
+            }
+            let params = match frame.get("params") {
+                None | Some(Value::Null) => Value::Object(Default::default()),
+                Some(params) => params.clone(),
+            };
+            return serde_json::from_value(params).with_context(|| format!("Unexpected response to method {}", method));
+        }
+    }
+
+    /// Payload of the next stream frame of any request, with the seq of that request
+    pub async fn next_stream<T: DeserializeOwned>(&mut self) -> Result<(u32, T)> {
+        loop {
+            let frame = self.client.recv_raw().await?;
+            let Some(seq) = frame.get("original_seq").and_then(Value::as_u64) else {
+                continue;
+            };
+            let data = frame.get("data").cloned().unwrap_or(Value::Null);
+            return Ok((seq as u32, serde_json::from_value(data).context("Unexpected stream frame")?));
+        }
+    }
+
+    /// Profile of a user
+    pub async fn user_get_profile(&mut self, req: UserGetProfileReq) -> Result<UserGetProfileResp> {
+        self.call(100, req.to_params()?).await
+    }
+
+    /// Streams changes to a profile
+    pub async fn user_watch_profile(&mut self, req: UserWatchProfileReq) -> Result<UserWatchProfileResp> {
+        self.call(101, req.to_params()?).await
+    }
+
+    /// Profile of a user as admins see it
+    pub async fn admin_get_profile(&mut self, req: AdminGetProfileReq) -> Result<AdminGetProfileResp> {
+        self.call(200, req.to_params()?).await
+    }
+}
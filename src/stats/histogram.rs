@@ -0,0 +1,30 @@
+/// Upper bounds of the histogram buckets in milliseconds, the last bucket
+/// taking everything slower
+const BUCKET_BOUNDS_MS: [u64; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000];
+
+/// Latency samples in milliseconds as text histogram lines, one per bucket
+/// from the fastest to the slowest non-empty one, bars scaled to `bar_width`
+pub fn histogram(samples: &[u64], bar_width: usize) -> Vec<String> {
+    let mut counts = [0usize; BUCKET_BOUNDS_MS.len() + 1];
+    for &ms in samples {
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|&bound| ms < bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+        counts[bucket] += 1;
+    }
+    let Some(last) = counts.iter().rposition(|&count| count > 0) else {
+        return Vec::new();
+    };
+    let first = counts.iter().position(|&count| count > 0).unwrap_or_default();
+    let most = counts.iter().copied().max().unwrap_or_default();
+
+    (first..=last)
+        .map(|bucket| {
+            let label = match bucket {
+                0 => format!("<{}ms", BUCKET_BOUNDS_MS[0]),
+                _ if bucket == BUCKET_BOUNDS_MS.len() => format!(">={}ms", BUCKET_BOUNDS_MS[bucket - 1]),
+                _ => format!("{}-{}ms", BUCKET_BOUNDS_MS[bucket - 1], BUCKET_BOUNDS_MS[bucket]),
+            };
+            let bar = "#".repeat((counts[bucket] * bar_width).div_ceil(most));
+            format!("{:>12} {:<width$} {}", label, bar, counts[bucket], width = bar_width)
+        })
+        .collect()
+}
@@ -0,0 +1,221 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Samples kept per series; older ones are dropped so long sessions stay bounded
+const MAX_SAMPLES: usize = 10_000;
+
+/// Latency samples in milliseconds, oldest first
+#[derive(Default)]
+pub struct LatencyStats {
+    samples: VecDeque<u64>,
+}
+
+/// Summary of a series of latency samples
+pub struct LatencySummary {
+    pub count: usize,
+    pub min: u64,
+    pub avg: u64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, ms: u64) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        summarize(&self.samples.iter().copied().collect::<Vec<_>>())
+    }
+}
+
+/// Summary of latency samples in milliseconds, in any order
+pub fn summarize(samples: &[u64]) -> Option<LatencySummary> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let count = sorted.len();
+    // Nearest-rank percentile
+    let p95 = sorted[(count * 95).div_ceil(100).max(1) - 1];
+    Some(LatencySummary {
+        count,
+        min: sorted[0],
+        avg: sorted.iter().sum::<u64>() / count as u64,
+        p95,
+        max: sorted[count - 1],
+    })
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} min {}ms avg {}ms p95 {}ms max {}ms",
+            self.count, self.min, self.avg, self.p95, self.max
+        )
+    }
+}
+
+/// Latency of each endpoint, and the frame intervals of stream endpoints
+#[derive(Default)]
+pub struct EndpointStats {
+    pub latency: BTreeMap<String, LatencyStats>,
+    pub intervals: BTreeMap<String, LatencyStats>,
+}
+
+impl EndpointStats {
+    /// Time from sending a request to its response, or to the first frame of a stream
+    pub fn record_latency(&mut self, endpoint: &str, ms: u64) {
+        self.latency.entry(endpoint.to_string()).or_default().record(ms);
+    }
+
+    /// Time between two frames of a stream
+    pub fn record_interval(&mut self, endpoint: &str, ms: u64) {
+        self.intervals.entry(endpoint.to_string()).or_default().record(ms);
+    }
+
+    /// One line per endpoint with samples, sorted by endpoint name
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (endpoint, stats) in &self.latency {
+            let Some(summary) = stats.summary() else {
+                continue;
+            };
+            lines.push(format!("{}: {}", endpoint, summary));
+            if let Some(intervals) = self.intervals.get(endpoint).and_then(LatencyStats::summary) {
+                lines.push(format!("{} frame interval: {}", endpoint, intervals));
+            }
+        }
+        lines
+    }
+}
+
+/// Timing of a request from the moment it was sent
+pub struct RequestTiming {
+    pub endpoint: String,
+    pub sent_at: Instant,
+    /// Time to the response, or to the first frame of a stream
+    pub first_frame: Option<Duration>,
+    pub last_frame_at: Option<Instant>,
+    pub frames: usize,
+    /// Time between the stream's frames so far
+    pub intervals: LatencyStats,
+}
+
+/// What a frame added to a request's timing
+pub enum FrameTiming {
+    First(u64),
+    Interval(u64),
+}
+
+impl RequestTiming {
+    pub fn start(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            sent_at: Instant::now(),
+            first_frame: None,
+            last_frame_at: None,
+            frames: 0,
+            intervals: LatencyStats::default(),
+        }
+    }
+
+    /// Records a frame answering the request
+    pub fn frame(&mut self) -> FrameTiming {
+        let now = Instant::now();
+        self.frames += 1;
+        let timing = match self.last_frame_at {
+            None => {
+                let first = now - self.sent_at;
+                self.first_frame = Some(first);
+                FrameTiming::First(first.as_millis() as u64)
+            }
+            Some(last) => {
+                let interval = (now - last).as_millis() as u64;
+                self.intervals.record(interval);
+                FrameTiming::Interval(interval)
+            }
+        };
+        self.last_frame_at = Some(now);
+        timing
+    }
+
+    /// Short description for the Response block title
+    pub fn describe(&self, is_stream: bool) -> String {
+        let Some(first) = self.first_frame else {
+            return "waiting".to_string();
+        };
+        if !is_stream {
+            return format!("{}ms", first.as_millis());
+        }
+        let mut text = format!("first frame {}ms, {} frames", first.as_millis(), self.frames);
+        if let Some(intervals) = self.intervals.summary() {
+            text.push_str(&format!(", interval avg {}ms p95 {}ms", intervals.avg, intervals.p95));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(summary: LatencySummary) -> (usize, u64, u64, u64, u64) {
+        (summary.count, summary.min, summary.avg, summary.p95, summary.max)
+    }
+
+    #[test]
+    fn summary_uses_the_nearest_rank_p95() {
+        assert!(summarize(&[]).is_none());
+        assert_eq!(parts(summarize(&[7]).unwrap()), (1, 7, 7, 7, 7));
+        let hundred: Vec<u64> = (1..=100).rev().collect();
+        assert_eq!(parts(summarize(&hundred).unwrap()), (100, 1, 50, 95, 100));
+        let twenty: Vec<u64> = (1..=20).collect();
+        assert_eq!(parts(summarize(&twenty).unwrap()), (20, 1, 10, 19, 20));
+        assert_eq!(parts(summarize(&[1, 1000]).unwrap()), (2, 1, 500, 1000, 1000));
+    }
+
+    #[test]
+    fn stats_keep_the_latest_samples() {
+        let mut stats = LatencyStats::default();
+        for ms in 0..MAX_SAMPLES as u64 + 10 {
+            stats.record(ms);
+        }
+        let summary = stats.summary().unwrap();
+        assert_eq!((summary.count, summary.min, summary.max), (MAX_SAMPLES, 10, MAX_SAMPLES as u64 + 9));
+    }
+
+    #[test]
+    fn report_lists_latency_then_frame_intervals() {
+        let mut stats = EndpointStats::default();
+        stats.record_latency("Watch", 30);
+        stats.record_interval("Watch", 100);
+        stats.record_interval("Watch", 300);
+        stats.record_latency("Get", 5);
+        assert_eq!(
+            stats.report(),
+            vec![
+                "Get: n=1 min 5ms avg 5ms p95 5ms max 5ms".to_string(),
+                "Watch: n=1 min 30ms avg 30ms p95 30ms max 30ms".to_string(),
+                "Watch frame interval: n=2 min 100ms avg 200ms p95 300ms max 300ms".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_timing_records_intervals_after_the_first_frame() {
+        let mut timing = RequestTiming::start("Watch");
+        assert_eq!(timing.describe(true), "waiting");
+        assert!(matches!(timing.frame(), FrameTiming::First(_)));
+        assert!(matches!(timing.frame(), FrameTiming::Interval(_)));
+        assert!(matches!(timing.frame(), FrameTiming::Interval(_)));
+        assert_eq!(timing.frames, 3);
+        assert_eq!(timing.intervals.summary().map(|summary| summary.count), Some(2));
+    }
+}
@@ -0,0 +1,5 @@
+mod histogram;
+mod latency;
+
+pub use histogram::*;
+pub use latency::*;
@@ -1,233 +1,360 @@
-use crate::tui::state::{AppState, JsonViewMode, EndpointField};
+use crate::tui::clipboard::copy_to_clipboard;
+use crate::tui::history::History;
+use crate::tui::keymap::{Action, Keymap};
+use crate::tui::state::{AppBlock, AppState, ConnectRequest};
 use crate::tui::ui::draw_ui;
-use crate::parser::{EndpointMetadata};
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use crate::parser::{EndpointDefaults, EndpointMetadata, TypeRegistry};
+use crate::ws::{WsClient, WsReceiver, WsSender};
+use crate::tui::terminal::TerminalGuard;
+use crate::logging::LogBuffer;
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyModifiers, MouseButton, MouseEventKind};
 use crossterm::execute;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::io;
-use anyhow::{Result, Context};
+use anyhow::{anyhow, Context, Result};
+use futures::StreamExt;
+use serde_json::Value;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{self, Duration};
-
-pub async fn run(endpoint_names: Vec<String>, endpoint_data: HashMap<String, EndpointMetadata>, param_defaults: Vec<(String, Vec<(String, String)>)>) -> Result<()> {
-    // Set up terminal in raw mode
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let terminal = Arc::new(Mutex::new(Terminal::new(backend)?));
-
-    // Initialize app state with shared state
-    let app_state = Arc::new(Mutex::new(AppState::new(endpoint_names, endpoint_data, param_defaults)));
-
-    // Spawn a task to handle TUI updates
-    let terminal_clone = Arc::clone(&terminal);
-    let app_state_clone = Arc::clone(&app_state);
-    tokio::spawn(async move {
-        let mut ticker = time::interval(Duration::from_millis(500));
-        loop {
-            ticker.tick().await;  // Wait for the next tick
-            let mut app_state_guard = app_state_clone.lock().await;
-            let mut terminal_guard = terminal_clone.lock().await;
-            if let Err(e) = terminal_guard.draw(|f| draw_ui(f, &mut *app_state_guard)) {
-                eprintln!("Error drawing UI: {}", e);
-                break;  // Exit the loop if drawing fails
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, warn};
+
+/// How often changed history is written to disk
+const HISTORY_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long connecting and logging in may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the UI loop hears from the tasks it spawned
+pub enum AppEvent {
+    /// A frame read from connection `connection_id`, or the error that ended it
+    Frame { connection_id: u64, frame: Result<Value> },
+    /// Connection `connection_id` is open and logged in, with the login
+    /// response, or failed to
+    Connected { connection_id: u64, result: Result<(WsSender, WsReceiver, Value)> },
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(endpoint_names: Vec<String>, endpoint_data: HashMap<String, EndpointMetadata>, registry: TypeRegistry, param_defaults: Vec<EndpointDefaults>, history: History, config_path: String, keymap: Keymap, logs: LogBuffer) -> Result<()> {
+    // Restores the terminal on every way out of here, panics included
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let mut app_state = AppState::new(endpoint_names, endpoint_data, registry, param_defaults, history, config_path, keymap, logs);
+    let mut history_writer = HistoryWriter::default();
+    let result = event_loop(&mut terminal, &mut app_state, &mut history_writer).await;
+    if let Err(err) = &result {
+        error!("UI loop failed: {:#}", err);
+    }
+    history_writer.finish(&mut app_state.history).await;
+    result
+}
+
+/// The only owner of the state: handles terminal input and network events
+/// one at a time and redraws after each, so nothing waits on a lock
+async fn event_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app_state: &mut AppState, history_writer: &mut HistoryWriter) -> Result<()> {
+    let (events, mut network) = mpsc::unbounded_channel();
+    let mut input = EventStream::new();
+    let mut history_save = tokio::time::interval(HISTORY_SAVE_INTERVAL);
+    history_save.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    terminal.draw(|f| draw_ui(f, app_state))?;
+
+    loop {
+        tokio::select! {
+            event = input.next() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                match handle_event(terminal, app_state, &events, event?).await? {
+                    Flow::Redraw => {}
+                    Flow::Skip => continue,
+                    Flow::Quit => return Ok(()),
+                }
+            }
+            Some(event) = network.recv() => handle_app_event(app_state, &events, event),
+            _ = history_save.tick() => {
+                history_writer.save(&mut app_state.history);
+                continue;
             }
         }
-    });
 
-    // Spawn a task to handle input events
-    let terminal_clone = Arc::clone(&terminal);
-    let handle_event_task = tokio::spawn(async move {
-        loop {
-            if let Err(e) = handle_event(&app_state, &terminal_clone).await {
-                eprintln!("Error handling event: {}", e);
-                break;
+        // A fast stream delivers frames in bursts: apply them all before drawing once
+        while let Ok(event) = network.try_recv() {
+            handle_app_event(app_state, &events, event);
+        }
+        terminal.draw(|f| draw_ui(f, app_state))?;
+    }
+}
+
+/// Writes history snapshots from a task of their own, one at a time, so
+/// sends and streams never wait on the disk
+#[derive(Default)]
+struct HistoryWriter {
+    pending: Option<JoinHandle<()>>,
+}
+
+impl HistoryWriter {
+    /// Starts a write if the history changed and the last write is done
+    fn save(&mut self, history: &mut History) {
+        if self.pending.as_ref().is_some_and(|write| !write.is_finished()) {
+            return;
+        }
+        match history.snapshot() {
+            Ok(Some((path, content))) => {
+                self.pending = Some(tokio::spawn(async move {
+                    if let Err(err) = tokio::fs::write(&path, content).await {
+                        error!("Failed to write history file {}: {}", path.display(), err);
+                    }
+                }));
             }
+            Ok(None) => {}
+            Err(err) => error!("{:#}", err),
         }
-    });
+    }
 
-    // Wait for the handle_event_task to finish, which will happen when Esc is pressed
-    handle_event_task.await?;
-
-    // Perform terminal cleanup in the async context
-    {
-        let mut terminal = terminal.lock().await;
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
+    /// Waits for a running write, then writes whatever changed since
+    async fn finish(self, history: &mut History) {
+        if let Some(write) = self.pending {
+            let _ = write.await;
+        }
+        if let Err(err) = history.save() {
+            error!("{:#}", err);
+        }
     }
+}
 
-    Ok(())
+fn handle_app_event(app_state: &mut AppState, events: &UnboundedSender<AppEvent>, event: AppEvent) {
+    match event {
+        AppEvent::Connected { connection_id, result } => {
+            let (result, receiver) = match result {
+                Ok((sender, receiver, login)) => (Ok((sender, login)), Some(receiver)),
+                Err(err) => (Err(err), None),
+            };
+            if app_state.connect_finished(connection_id, result) {
+                if let Some(receiver) = receiver {
+                    spawn_listener(events.clone(), receiver, connection_id);
+                }
+            }
+        }
+        AppEvent::Frame { connection_id, frame } => {
+            if app_state.connection_id != connection_id || !app_state.connected {
+                // The user disconnected or reconnected, this connection is stale
+                return;
+            }
+            match frame {
+                Ok(frame) => app_state.dispatch_frame(frame),
+                Err(e) => {
+                    warn!("Connection lost: {:#}", e);
+                    app_state.connection_lost(e);
+                }
+            }
+        }
+    }
 }
 
-async fn handle_event(app_state: &Arc<Mutex<AppState>>, terminal: &Arc<Mutex<Terminal<CrosstermBackend<std::io::Stdout>>>>) -> Result<()> {
-    // Poll for events with a short timeout
-    if event::poll(Duration::from_millis(100))? {
-        if let Event::Key(key) = event::read()? {
-            let mut needs_redraw = false;
+/// What the loop does after an input event
+enum Flow {
+    Redraw,
+    /// Nothing visible changed
+    Skip,
+    Quit,
+}
 
-            match key.code {
-                KeyCode::Char(c) => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.update_input(c);
-                    needs_redraw = true;
+async fn handle_event(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app_state: &mut AppState, events: &UnboundedSender<AppEvent>, event: Event) -> Result<Flow> {
+    if let Event::Paste(text) = &event {
+        app_state.paste(text);
+    }
+    if let Event::Mouse(mouse) = event {
+        if app_state.popup_open() {
+            return Ok(Flow::Skip);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                app_state.status_message = None;
+                if app_state.click(mouse.column, mouse.row) {
+                    press_enter(app_state, events).await;
                 }
+            }
+            MouseEventKind::ScrollDown => app_state.scroll(mouse.column, mouse.row, true),
+            MouseEventKind::ScrollUp => app_state.scroll(mouse.column, mouse.row, false),
+            // Moves and drags would only redraw for nothing
+            _ => return Ok(Flow::Skip),
+        }
+    }
+    if let Event::Key(key) = event {
+        app_state.status_message = None;
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if app_state.quit_confirm {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => return Ok(Flow::Quit),
+                _ => app_state.quit_confirm = false,
+            }
+        } else if app_state.help_open || app_state.stats_open {
+            // Any key closes the help and stats overlays
+            app_state.help_open = false;
+            app_state.stats_open = false;
+        } else if app_state.history_open && app_state.keymap.action(&key, true) == Some(Action::EditHistory) {
+            if let Err(err) = app_state.rerun_history(false).await {
+                error!("Failed to load history entry: {:#}", err);
+                app_state.status_message = Some(err.to_string());
+            }
+        } else if app_state.history_open && !control {
+            handle_history_key(app_state, key.code).await;
+        } else if app_state.save_prompt.is_some() && !control {
+            match key.code {
+                KeyCode::Char(c) => app_state.save_prompt.get_or_insert_with(String::new).push(c),
                 KeyCode::Backspace => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.delete_last_char();
-                    needs_redraw = true;
-                }
-                KeyCode::Tab => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.switch_block();
-                    needs_redraw = true;
-                }
-                KeyCode::Left => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.scroll_response_left();
-                    needs_redraw = true;
-                }
-                KeyCode::Right => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.scroll_response_right();
-                    needs_redraw = true;
+                    app_state.save_prompt.get_or_insert_with(String::new).pop();
                 }
-                KeyCode::Down => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.next_field();
-                    needs_redraw = true;
+                KeyCode::Enter => app_state.save_params(),
+                KeyCode::Esc => app_state.save_prompt = None,
+                _ => {}
+            }
+        } else if app_state.export_open && !control {
+            match key.code {
+                KeyCode::Down => app_state.export_next(),
+                KeyCode::Up => app_state.export_previous(),
+                KeyCode::Enter => app_state.export_selected_kind(),
+                KeyCode::Esc => app_state.toggle_export(),
+                _ => {}
+            }
+        } else if app_state.search_editing && !control {
+            match key.code {
+                KeyCode::Char(c) => app_state.search_input(c),
+                KeyCode::Backspace => app_state.search_delete_last_char(),
+                KeyCode::Enter => app_state.finish_search(),
+                KeyCode::Esc => app_state.cancel_search(),
+                _ => {}
+            }
+        } else if app_state.endpoint_filter_editing && !control {
+            match key.code {
+                KeyCode::Char(c) => app_state.update_input(c),
+                KeyCode::Backspace => app_state.delete_last_char(),
+                KeyCode::Down => app_state.next_field(),
+                KeyCode::Up => app_state.previous_field(),
+                KeyCode::Enter | KeyCode::Esc => app_state.endpoint_filter_editing = false,
+                _ => {}
+            }
+        } else {
+            let typing = app_state.typing();
+            match app_state.keymap.action(&key, typing) {
+                Some(Action::Quit) => {
+                    if app_state.request_quit() {
+                        return Ok(Flow::Quit);
+                    }
                 }
-                KeyCode::Up => {
-                    let mut app_state_guard = app_state.lock().await;
-                    app_state_guard.previous_field();
-                    needs_redraw = true;
+                Some(Action::CopyPointer) if app_state.current_block == AppBlock::EndpointsRes => {
+                    if let Some(pointer) = app_state.selected_pointer() {
+                        app_state.status_message = Some(match copy_to_clipboard(&pointer) {
+                            Ok(()) => format!("Copied JSON pointer {:?}", pointer),
+                            Err(err) => format!("Failed to copy JSON pointer: {}", err),
+                        });
+                    }
                 }
-                KeyCode::Enter => {
-                    let app_state_clone = Arc::clone(&app_state);
-
-                    // Lock the app_state and check the focused field
-                    let focused_field = {
-                        let state = app_state_clone.lock().await;
-                        state.focused_endpoint_field.clone()
-                    };
-
-                    // Now move the app_state_clone and start the connection
-                    if let Some(EndpointField::ConnectButton) = focused_field {
-                        if let Err(e) = connect_and_listen(app_state_clone).await {
-                            eprintln!("Error connecting and listening: {}", e);
-                        }
+                Some(Action::ToggleMouse) => {
+                    let captured = !app_state.mouse_captured;
+                    app_state.mouse_captured = captured;
+                    if captured {
+                        execute!(terminal.backend_mut(), EnableMouseCapture)?;
                     } else {
-                        let mut app_state_guard = app_state.lock().await;
-                        if let Err(err) = app_state_guard.handle_enter().await {
-                            app_state_guard.json_data = Some(format!("Error: {}", err));
-                        }
+                        execute!(terminal.backend_mut(), DisableMouseCapture)?;
                     }
-                    needs_redraw = true;
-                }
-                KeyCode::Esc => {
-                    // Return an error to indicate that the program should exit
-                    return Err(anyhow::anyhow!("Esc pressed, exiting program"));
-                }
-                _ => {}
-            }
-
-            // Redraw TUI after handling key events only if necessary
-            if needs_redraw {
-                let mut app_state_guard = app_state.lock().await;
-                let mut terminal_guard = terminal.lock().await;
-                if let Err(e) = terminal_guard.draw(|f| draw_ui(f, &mut *app_state_guard)) {
-                    eprintln!("Error drawing UI: {}", e);
+                    let keys = app_state.keymap.keys(Action::ToggleMouse);
+                    app_state.status_message = Some(if captured {
+                        "Mouse captured".to_string()
+                    } else {
+                        format!("Mouse released, select text freely. {} to capture it again", keys)
+                    });
                 }
+                Some(action) => app_state.perform(action),
+                None => match key.code {
+                    KeyCode::Char(c) if !control => app_state.update_input(c),
+                    KeyCode::Backspace => app_state.delete_last_char(),
+                    KeyCode::Insert => app_state.insert_param_item(),
+                    KeyCode::Delete => app_state.delete_forward(),
+                    KeyCode::Enter => press_enter(app_state, events).await,
+                    _ => {}
+                },
             }
         }
     }
 
-    Ok(())
+    Ok(Flow::Redraw)
 }
 
-async fn connect_and_listen(app_state: Arc<Mutex<AppState>>) -> Result<()> {
-    tokio::spawn(async move {
-        let (method_id, converted_params, is_stream) = {
-            let state = app_state.lock().await;
-
-            // Extract necessary data while holding the lock
-            let method_id = state.method_id.ok_or_else(|| anyhow::anyhow!("Method ID is missing"))?;
-            let is_stream = state.is_stream;
+/// Enter, or a click on a button: presses the focused button
+async fn press_enter(app_state: &mut AppState, events: &UnboundedSender<AppEvent>) {
+    if let Err(err) = app_state.handle_enter().await {
+        error!("{:#}", err);
+        app_state.tab_mut().show_message(format!("Error: {}", err));
+    }
 
-            let converted_params = state.params.iter().zip(state.param_values.iter())
-                .map(|(param, value)| {
-                    param.ty.convert_value(value)
-                        .context(format!("Failed to convert value for parameter: {}", param.name))
-                })
-                .collect::<Result<Vec<_>>>()?;
+    if let Some(request) = app_state.connect_request.take() {
+        spawn_connect(events.clone(), request, app_state.connection_id);
+    }
+}
 
-            (method_id, converted_params, is_stream)
+/// Connects and reads the login response off the UI loop, giving up after
+/// `CONNECT_TIMEOUT`, and hands the outcome to the loop as `AppEvent::Connected`
+fn spawn_connect(events: UnboundedSender<AppEvent>, request: ConnectRequest, connection_id: u64) {
+    tokio::spawn(async move {
+        let connect = async {
+            let client = WsClient::new(&request.url, &request.header).await.context("Failed to connect to WebSocket")?;
+            let (sender, mut receiver) = client.split();
+            let login = receiver.recv_raw().await.context("Failed to receive response from WebSocket")?;
+            Ok((sender, receiver, login))
         };
+        let result = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("Timed out after {}s connecting to {}", CONNECT_TIMEOUT.as_secs(), request.url)),
+        };
+        let _ = events.send(AppEvent::Connected { connection_id, result });
+    });
+}
 
-        {
-            // Send the request to the WebSocket
-            let mut state = app_state.lock().await;
-            let client = state.client.as_mut().context("WebSocket client is not connected")?;
-            client.send_req(method_id, converted_params).await.context("Failed to send request to WebSocket")?;
+/// Keys while the history panel is open: typing searches, Enter re-runs the
+/// selected entry and Esc closes the panel
+async fn handle_history_key(app_state: &mut AppState, code: KeyCode) {
+    match code {
+        KeyCode::Char(c) => app_state.history_input(c),
+        KeyCode::Backspace => app_state.history_delete_last_char(),
+        KeyCode::Down => app_state.history_next(),
+        KeyCode::Up => app_state.history_previous(),
+        KeyCode::Enter => {
+            if let Err(err) = app_state.rerun_history(true).await {
+                error!("Failed to re-run history entry: {:#}", err);
+                app_state.tab_mut().show_message(format!("Error: {}", err));
+            }
         }
+        KeyCode::Esc => app_state.toggle_history(),
+        _ => {}
+    }
+}
 
-        // Enter the receiving loop
+/// Reads every frame from the shared connection and hands it to the UI
+/// loop, which routes it to the tab that sent the matching request. If the
+/// listener panics the connection is reported lost.
+fn spawn_listener(events: UnboundedSender<AppEvent>, mut receiver: WsReceiver, connection_id: u64) {
+    let frames = events.clone();
+    let listener = tokio::spawn(async move {
         loop {
-            let raw_response_result = {
-                let mut state = app_state.lock().await;
-                let client = state.client.as_mut().context("WebSocket client is not connected")?;
-                
-                client.recv_raw().await
-            };
-
-            match raw_response_result {
-                Ok(raw_response) => {
-                    let resp = {
-                        let state = app_state.lock().await;
-                        match state.json_view_mode {
-                            JsonViewMode::Pretty => serde_json::to_string_pretty(&raw_response).context("Failed to format JSON as pretty"),
-                            JsonViewMode::Raw => serde_json::to_string(&raw_response).context("Failed to format JSON as raw"),
-                        }
-                    };
-
-                    let mut state = app_state.lock().await;
-                    match resp {
-                        Ok(formatted_json) => {
-                            state.json_data = Some(formatted_json);
-                            state.endpoint_connected = true;
-                        }
-                        Err(err) => {
-                            state.json_data = Some(format!("Error: {}", err));
-                        }
-                    }
-                }
-                Err(e) => {
-                    let mut state = app_state.lock().await;
-                    state.json_data = Some(format!("Error receiving data: {:?}", e));
-                    break;
-                }
-            }
-
-            if !is_stream {
-                // If not streaming, break after receiving one response
+            let frame = receiver.recv_raw().await;
+            let failed = frame.is_err();
+            if frames.send(AppEvent::Frame { connection_id, frame }).is_err() || failed {
                 break;
             }
-
-            // Optional: sleep before the next iteration if streaming
-            tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
         }
-
-        Ok::<(), anyhow::Error>(())
     });
-
-    Ok(())
+    tokio::spawn(async move {
+        if let Err(err) = listener.await {
+            if err.is_panic() {
+                let frame = Err(anyhow!("Frame listener panicked, see the log"));
+                let _ = events.send(AppEvent::Frame { connection_id, frame });
+            }
+        }
+    });
 }
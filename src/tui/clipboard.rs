@@ -0,0 +1,14 @@
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, Write};
+
+/// Copies `text` to the system clipboard with an OSC 52 escape sequence,
+/// which most terminals (and tmux with `set-clipboard on`) pass through,
+/// also over SSH
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
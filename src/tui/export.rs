@@ -0,0 +1,137 @@
+use crate::time::now_ms;
+use crate::ws::login_header;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportKind {
+    ResponseJson,
+    ResponseNdjson,
+    RequestJson,
+    RequestNdjson,
+    StreamLogJson,
+    StreamLogNdjson,
+    RequestWebsocat,
+}
+
+impl ExportKind {
+    pub const ALL: [ExportKind; 7] = [
+        ExportKind::ResponseJson,
+        ExportKind::ResponseNdjson,
+        ExportKind::RequestJson,
+        ExportKind::RequestNdjson,
+        ExportKind::StreamLogJson,
+        ExportKind::StreamLogNdjson,
+        ExportKind::RequestWebsocat,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportKind::ResponseJson => "Response as pretty JSON",
+            ExportKind::ResponseNdjson => "Response as NDJSON",
+            ExportKind::RequestJson => "Last request as pretty JSON",
+            ExportKind::RequestNdjson => "Last request as NDJSON",
+            ExportKind::StreamLogJson => "Stream log as pretty JSON",
+            ExportKind::StreamLogNdjson => "Stream log as NDJSON",
+            ExportKind::RequestWebsocat => "Last request as websocat command",
+        }
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        match self {
+            ExportKind::ResponseJson => "response.json",
+            ExportKind::ResponseNdjson => "response.ndjson",
+            ExportKind::RequestJson => "request.json",
+            ExportKind::RequestNdjson => "request.ndjson",
+            ExportKind::StreamLogJson => "stream.json",
+            ExportKind::StreamLogNdjson => "stream.ndjson",
+            ExportKind::RequestWebsocat => "request.sh",
+        }
+    }
+}
+
+/// What a tab can export
+pub struct ExportSource<'a> {
+    pub endpoint: &'a str,
+    pub response: Option<&'a Value>,
+    pub request: Option<&'a Value>,
+    pub frames: &'a VecDeque<Value>,
+    pub url: &'a str,
+    pub username: &'a str,
+}
+
+/// Renders the export. The websocat command reads the password from
+/// `$PASSWORD` so exports can be attached to bug reports as they are.
+pub fn render_export(kind: ExportKind, source: &ExportSource) -> Result<String> {
+    let response = || source.response.context("There is no response to export");
+    let request = || source.request.context("No request has been sent from this tab");
+
+    let content = match kind {
+        ExportKind::ResponseJson => serde_json::to_string_pretty(response()?)?,
+        ExportKind::ResponseNdjson => serde_json::to_string(response()?)?,
+        ExportKind::RequestJson => serde_json::to_string_pretty(request()?)?,
+        ExportKind::RequestNdjson => serde_json::to_string(request()?)?,
+        ExportKind::StreamLogJson => serde_json::to_string_pretty(source.frames)?,
+        ExportKind::StreamLogNdjson => source.frames
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n"),
+        ExportKind::RequestWebsocat => {
+            let request = serde_json::to_string(request()?)?;
+            // Everything but the password is single-quoted, so the username
+            // can't break out of the string; the password expands in between
+            let header = login_header(source.username, "$PASSWORD");
+            let (before, after) = header.rsplit_once("$PASSWORD").context("Login header has no password")?;
+            format!(
+                "#!/bin/sh\n# Reproduces {} against {}\necho {} | websocat -n --protocol {}\"$PASSWORD\"{} {}",
+                source.endpoint,
+                source.url,
+                shell_quote(&request),
+                shell_quote(before),
+                shell_quote(after),
+                shell_quote(source.url)
+            )
+        }
+    };
+    Ok(content + "\n")
+}
+
+/// Writes the export into `dir` as `<endpoint>-<unix ms>-<suffix>` and returns its path
+pub fn write_export(kind: ExportKind, source: &ExportSource, dir: &Path) -> Result<PathBuf> {
+    let content = render_export(kind, source)?;
+    let endpoint = if source.endpoint.is_empty() { "export" } else { source.endpoint };
+    let path = dir.join(format!("{}-{}-{}", endpoint, now_ms(), kind.file_suffix()));
+    fs::write(&path, content).with_context(|| format!("Failed to write export file: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Single-quotes `text` for a POSIX shell
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocat_script_quotes_the_username() {
+        let request = serde_json::json!({"method": 200, "seq": 1, "params": []});
+        let source = ExportSource {
+            endpoint: "GetUser",
+            response: None,
+            request: Some(&request),
+            frames: &VecDeque::new(),
+            url: "wss://example.com",
+            username: "a\"b`c$d\\e'f",
+        };
+        let script = render_export(ExportKind::RequestWebsocat, &source).unwrap();
+        assert!(script.contains(
+            "--protocol '0login, 1a\"b`c$d\\e'\\''f, 2'\"$PASSWORD\"', 3User, 424787297130491616, 5android' "
+        ));
+    }
+}
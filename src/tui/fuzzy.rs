@@ -0,0 +1,54 @@
+/// Scores `text` against `pattern` as a case-insensitive subsequence match.
+/// Returns `None` when not every pattern char occurs in order. Consecutive
+/// matches and matches at the start of a word (after `_`, `-`, space, at a
+/// lowercase to uppercase change or at the beginning) score higher, so `ugp`
+/// ranks `user_get_profile` first and `gup` ranks `GetUserProfile` first.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().map(|&c| lowercase(c)).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for pattern_char in pattern.chars().map(lowercase) {
+        let found = (position..text.len()).find(|&i| text[i] == pattern_char)?;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        let camel_hump = found > 0 && original[found - 1].is_lowercase() && original[found].is_uppercase();
+        if found == 0 || camel_hump || matches!(text[found - 1], '_' | '-' | ' ') {
+            score += 3;
+        }
+        // Gaps between matched chars cost a little
+        score -= (found - position) as i64 / 4;
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camel_case_humps_are_word_starts() {
+        let humps = fuzzy_score("gup", "GetUserProfile").unwrap();
+        let scattered = fuzzy_score("gup", "Getupdates").unwrap();
+        assert!(humps > scattered, "{} <= {}", humps, scattered);
+        assert_eq!(fuzzy_score("gup", "user_get_profile"), None);
+        assert!(fuzzy_score("ugp", "user_get_profile").unwrap() > fuzzy_score("ugp", "UpgradePlan").unwrap());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::time::{format_timestamp, now_ms};
+use crate::ws::response_error_code;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Oldest entries are dropped past this
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RequestStatus {
+    Pending,
+    Ok,
+    Error(Option<i64>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub endpoint: String,
+    pub method_id: u32,
+    /// Raw values as typed in the Request block, keyed by parameter name
+    pub params: Vec<(String, String)>,
+    pub timestamp_ms: u64,
+    pub response: Option<Value>,
+    pub latency_ms: Option<u64>,
+    pub status: RequestStatus,
+}
+
+impl HistoryEntry {
+    pub fn new(endpoint: String, method_id: u32, params: Vec<(String, String)>) -> Self {
+        Self {
+            endpoint,
+            method_id,
+            params,
+            timestamp_ms: now_ms(),
+            response: None,
+            latency_ms: None,
+            status: RequestStatus::Pending,
+        }
+    }
+
+    /// Fills in the outcome from the first response frame
+    pub fn complete(&mut self, response: &Value, latency_ms: u64) {
+        self.status = match response_error_code(response) {
+            Some(code) => RequestStatus::Error(code),
+            None => RequestStatus::Ok,
+        };
+        self.response = Some(response.clone());
+        self.latency_ms = Some(latency_ms);
+    }
+
+    pub fn matches(&self, query: &str) -> bool {
+        if query.is_empty() {
+            return true;
+        }
+        let query = query.to_lowercase();
+        self.endpoint.to_lowercase().contains(&query)
+            || self.method_id.to_string().contains(&query)
+            || self.params.iter().any(|(name, value)| {
+                name.to_lowercase().contains(&query) || value.to_lowercase().contains(&query)
+            })
+    }
+
+    pub fn summary(&self) -> String {
+        let status = match &self.status {
+            RequestStatus::Pending => "pending".to_string(),
+            RequestStatus::Ok => "ok".to_string(),
+            RequestStatus::Error(Some(code)) => format!("error {}", code),
+            RequestStatus::Error(None) => "error".to_string(),
+        };
+        let latency = self.latency_ms.map(|ms| format!("{}ms", ms)).unwrap_or_else(|| "-".to_string());
+        let params = self.params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "{} {} ({}) {} {} {}",
+            format_timestamp(self.timestamp_ms),
+            self.endpoint,
+            self.method_id,
+            status,
+            latency,
+            params
+        )
+    }
+}
+
+/// Every request sent from the TUI, persisted as JSON between sessions. Only
+/// the newest `MAX_HISTORY_ENTRIES` are kept, in memory and on disk.
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    path: PathBuf,
+    /// Entries trimmed from the front this session, see `push`
+    trimmed: usize,
+    /// Changed since the last `snapshot`
+    dirty: bool,
+}
+
+impl History {
+    /// Loads history from `path`; a missing file starts an empty history. A
+    /// file that can't be read is moved aside with a warning, so a corrupt
+    /// history never keeps the app from starting.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = match Self::read(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let backup = path.with_extension("json.corrupt");
+                match fs::rename(&path, &backup) {
+                    Ok(()) => warn!("{:#}, starting an empty history (the old file is at {})", err, backup.display()),
+                    Err(_) => warn!("{:#}, starting an empty history", err),
+                }
+                Vec::new()
+            }
+        };
+        let mut history = Self { entries, path, trimmed: 0, dirty: false };
+        history.trim();
+        history
+    }
+
+    fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse history file: {}", path.display()))
+    }
+
+    /// Writes the file now, for the way out of the app
+    pub fn save(&mut self) -> Result<()> {
+        if let Some((path, content)) = self.snapshot()? {
+            fs::write(&path, content).with_context(|| format!("Failed to write history file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Path and serialized entries if anything changed since the last
+    /// snapshot, for the UI loop to write off its own task
+    pub fn snapshot(&mut self) -> Result<Option<(PathBuf, String)>> {
+        if !self.dirty {
+            return Ok(None);
+        }
+        let content = serde_json::to_string(&self.entries).context("Failed to serialize history")?;
+        self.dirty = false;
+        Ok(Some((self.path.clone(), content)))
+    }
+
+    /// Appends an entry and returns its id. Ids stay valid for the whole
+    /// session while positions in `entries` shift as old entries are trimmed.
+    pub fn push(&mut self, entry: HistoryEntry) -> usize {
+        self.entries.push(entry);
+        self.dirty = true;
+        let id = self.trimmed + self.entries.len() - 1;
+        self.trim();
+        id
+    }
+
+    /// Entry by the id `push` returned, unless it has been trimmed since
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut HistoryEntry> {
+        let entry = self.entries.get_mut(id.checked_sub(self.trimmed)?)?;
+        self.dirty = true;
+        Some(entry)
+    }
+
+    fn trim(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        if excess > 0 {
+            self.entries.drain(..excess);
+            self.trimmed += excess;
+            self.dirty = true;
+        }
+    }
+
+    /// Indices of entries matching `query`, newest first
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        (0..self.entries.len())
+            .rev()
+            .filter(|&i| self.entries[i].matches(query))
+            .collect()
+    }
+}
+
+/// Default history location: `~/.endpoint_validator_history.json`
+pub fn default_history_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(".endpoint_validator_history.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("endpoint_validator_{}_{}.json", name, std::process::id()))
+    }
+
+    fn entry(i: usize) -> HistoryEntry {
+        HistoryEntry::new(format!("Endpoint{}", i), i as u32, Vec::new())
+    }
+
+    #[test]
+    fn trims_in_memory_and_keeps_ids() {
+        let mut history = History::load(temp_path("trim"));
+        let first = history.push(entry(0));
+        let ids: Vec<usize> = (1..MAX_HISTORY_ENTRIES + 10).map(|i| history.push(entry(i))).collect();
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert!(history.get_mut(first).is_none());
+
+        let last = *ids.last().unwrap();
+        assert_eq!(history.get_mut(last).unwrap().endpoint, format!("Endpoint{}", MAX_HISTORY_ENTRIES + 9));
+        assert_eq!(history.entries[0].endpoint, "Endpoint10");
+    }
+
+    #[test]
+    fn snapshots_only_when_changed() {
+        let path = temp_path("snapshot");
+        let mut history = History::load(path.clone());
+        assert!(history.snapshot().unwrap().is_none());
+        let id = history.push(entry(1));
+        assert!(history.snapshot().unwrap().is_some());
+        assert!(history.snapshot().unwrap().is_none());
+
+        history.get_mut(id).unwrap().complete(&serde_json::json!({"type": "Immediate", "seq": 1}), 5);
+        history.save().unwrap();
+        let saved = History::load(path.clone());
+        assert_eq!(saved.entries.len(), 1);
+        assert_eq!(saved.entries[0].status, RequestStatus::Ok);
+        assert_eq!(saved.entries[0].latency_ms, Some(5));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside() {
+        let path = temp_path("corrupt");
+        fs::write(&path, "{not json").unwrap();
+        let history = History::load(path.clone());
+        assert!(history.entries.is_empty());
+        let backup = path.with_extension("json.corrupt");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "{not json");
+        assert!(!path.exists());
+        let _ = fs::remove_file(backup);
+    }
+}
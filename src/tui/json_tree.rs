@@ -0,0 +1,178 @@
+use ratatui::widgets::ListState;
+use serde_json::Value;
+use std::collections::HashSet;
+
+pub enum TreeToken {
+    /// Start of an object or array, `len` is its number of members
+    Open { bracket: char, len: usize, collapsed: bool },
+    Close(char),
+    Scalar(Value),
+}
+
+/// One rendered line of the tree. `pointer` is the RFC 6901 JSON pointer of
+/// the node the line belongs to; closing brackets share their container's.
+pub struct TreeLine {
+    pub pointer: String,
+    pub depth: usize,
+    pub key: Option<String>,
+    pub token: TreeToken,
+    pub trailing_comma: bool,
+}
+
+impl TreeLine {
+    /// Plain text of the line exactly as the tree widget draws it, used for search
+    pub fn text(&self) -> String {
+        let mut text = "  ".repeat(self.depth);
+        if let Some(key) = &self.key {
+            text.push_str(&serde_json::to_string(key).unwrap_or_default());
+            text.push_str(": ");
+        }
+        let comma = if self.trailing_comma { "," } else { "" };
+        match &self.token {
+            TreeToken::Open { bracket, len, collapsed } => {
+                if *collapsed {
+                    text.push_str(&format!("{}…{}{} {}", bracket, closing(*bracket), comma, member_count(*bracket, *len)));
+                } else {
+                    text.push_str(&format!("{} {}", bracket, member_count(*bracket, *len)));
+                }
+            }
+            TreeToken::Close(bracket) => text.push_str(&format!("{}{}", bracket, comma)),
+            TreeToken::Scalar(value) => text.push_str(&format!("{}{}", value, comma)),
+        }
+        text
+    }
+}
+
+pub fn closing(bracket: char) -> char {
+    if bracket == '{' { '}' } else { ']' }
+}
+
+/// `3 keys` for objects, `3 items` for arrays
+pub fn member_count(bracket: char, len: usize) -> String {
+    if bracket == '{' {
+        format!("{} keys", len)
+    } else {
+        format!("{} items", len)
+    }
+}
+
+/// Collapsed nodes and the selected line of a tab's response tree
+#[derive(Default)]
+pub struct JsonTreeState {
+    pub collapsed: HashSet<String>,
+    pub selected: usize,
+    /// Keeps the scroll offset between draws
+    pub view: ListState,
+}
+
+impl JsonTreeState {
+    pub fn select_next(&mut self, line_count: usize) {
+        if self.selected + 1 < line_count {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn toggle(&mut self, lines: &[TreeLine]) {
+        if let Some(line) = self.container_at(lines) {
+            let pointer = line.pointer.clone();
+            if !self.collapsed.remove(&pointer) {
+                self.collapse(lines);
+            }
+        }
+    }
+
+    /// Collapses the selected container and moves the selection onto it
+    pub fn collapse(&mut self, lines: &[TreeLine]) {
+        if let Some(line) = self.container_at(lines) {
+            let pointer = line.pointer.clone();
+            self.selected = lines.iter().position(|line| line.pointer == pointer).unwrap_or(self.selected);
+            self.collapsed.insert(pointer);
+        }
+    }
+
+    pub fn expand(&mut self, lines: &[TreeLine]) {
+        if let Some(line) = self.container_at(lines) {
+            self.collapsed.remove(&line.pointer);
+        }
+    }
+
+    pub fn selected_pointer<'a>(&self, lines: &'a [TreeLine]) -> Option<&'a str> {
+        lines.get(self.selected).map(|line| line.pointer.as_str())
+    }
+
+    fn container_at<'a>(&self, lines: &'a [TreeLine]) -> Option<&'a TreeLine> {
+        lines
+            .get(self.selected)
+            .filter(|line| matches!(line.token, TreeToken::Open { .. } | TreeToken::Close(_)))
+    }
+}
+
+/// Key (objects only), JSON pointer and value of a container member
+type Member<'a> = (Option<String>, String, &'a Value);
+
+/// Flattens `value` into the lines currently visible, skipping the members
+/// of collapsed containers
+pub fn flatten(value: &Value, collapsed: &HashSet<String>) -> Vec<TreeLine> {
+    let mut lines = Vec::new();
+    push_node(&mut lines, value, String::new(), 0, None, false, collapsed);
+    lines
+}
+
+fn push_node(
+    lines: &mut Vec<TreeLine>,
+    value: &Value,
+    pointer: String,
+    depth: usize,
+    key: Option<String>,
+    trailing_comma: bool,
+    collapsed: &HashSet<String>,
+) {
+    let (bracket, close, members): (char, char, Vec<Member>) = match value {
+        Value::Object(map) => (
+            '{',
+            '}',
+            map.iter()
+                .map(|(k, v)| (Some(k.clone()), format!("{}/{}", pointer, escape_pointer(k)), v))
+                .collect(),
+        ),
+        Value::Array(items) => (
+            '[',
+            ']',
+            items.iter()
+                .enumerate()
+                .map(|(i, v)| (None, format!("{}/{}", pointer, i), v))
+                .collect(),
+        ),
+        scalar => {
+            lines.push(TreeLine { pointer, depth, key, token: TreeToken::Scalar(scalar.clone()), trailing_comma });
+            return;
+        }
+    };
+
+    let is_collapsed = collapsed.contains(&pointer);
+    lines.push(TreeLine {
+        pointer: pointer.clone(),
+        depth,
+        key,
+        token: TreeToken::Open { bracket, len: members.len(), collapsed: is_collapsed },
+        trailing_comma: is_collapsed && trailing_comma,
+    });
+    if is_collapsed {
+        return;
+    }
+
+    let last = members.len().saturating_sub(1);
+    for (i, (member_key, member_pointer, member)) in members.into_iter().enumerate() {
+        push_node(lines, member, member_pointer, depth + 1, member_key, i != last, collapsed);
+    }
+    lines.push(TreeLine { pointer, depth, key: None, token: TreeToken::Close(close), trailing_comma });
+}
+
+/// Escapes a key for use in a JSON pointer (`~` becomes `~0`, `/` becomes `~1`)
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
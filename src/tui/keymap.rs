@@ -0,0 +1,373 @@
+use crate::parser::{KeyList, KeymapConfig};
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+/// Everything a key can be bound to through the `[keymap]` config table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Help,
+    Logs,
+    Stats,
+    NextBlock,
+    History,
+    EditHistory,
+    Export,
+    SaveParams,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    DeleteWord,
+    TogglePassword,
+    CopyPointer,
+    ToggleMouse,
+    Up,
+    Down,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::Quit,
+        Action::Help,
+        Action::Logs,
+        Action::Stats,
+        Action::NextBlock,
+        Action::History,
+        Action::EditHistory,
+        Action::Export,
+        Action::SaveParams,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::DeleteWord,
+        Action::TogglePassword,
+        Action::CopyPointer,
+        Action::ToggleMouse,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Top,
+        Action::Bottom,
+        Action::PageUp,
+        Action::PageDown,
+    ];
+
+    /// Name of the action in the `[keymap]` table
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::Logs => "logs",
+            Action::Stats => "stats",
+            Action::NextBlock => "next_block",
+            Action::History => "history",
+            Action::EditHistory => "edit_history",
+            Action::Export => "export",
+            Action::SaveParams => "save_params",
+            Action::NewTab => "new_tab",
+            Action::CloseTab => "close_tab",
+            Action::NextTab => "next_tab",
+            Action::PreviousTab => "previous_tab",
+            Action::DeleteWord => "delete_word",
+            Action::TogglePassword => "toggle_password",
+            Action::CopyPointer => "copy_pointer",
+            Action::ToggleMouse => "toggle_mouse",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide this help",
+            Action::Logs => "Show or hide the log pane",
+            Action::Stats => "Show latency stats per endpoint",
+            Action::NextBlock => "Switch to the next block",
+            Action::History => "Open request history",
+            Action::EditHistory => "Edit the selected history request before sending",
+            Action::Export => "Export response, request or stream log",
+            Action::SaveParams => "Save params to the config file",
+            Action::NewTab => "Open a request tab",
+            Action::CloseTab => "Close the request tab",
+            Action::NextTab => "Next request tab",
+            Action::PreviousTab => "Previous request tab",
+            Action::DeleteWord => "Delete the word before the cursor",
+            Action::TogglePassword => "Show or mask the password",
+            Action::CopyPointer => "Copy the JSON pointer of the selected response node",
+            Action::ToggleMouse => "Release the mouse to select text, or capture it again",
+            Action::Up => "Previous field, endpoint or line",
+            Action::Down => "Next field, endpoint or line",
+            Action::Left => "Cursor left, previous preset or variant, fold",
+            Action::Right => "Cursor right, next preset or variant, unfold",
+            Action::Top => "Start of the text or response",
+            Action::Bottom => "End of the text or response",
+            Action::PageUp => "Page up in the response or docs",
+            Action::PageDown => "Page down in the response or docs",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["esc", "q"],
+            Action::Help => &["?", "f1"],
+            Action::Logs => &["ctrl+l"],
+            Action::Stats => &["f3"],
+            Action::NextBlock => &["tab"],
+            Action::History => &["ctrl+r"],
+            Action::EditHistory => &["ctrl+e"],
+            Action::Export => &["ctrl+x"],
+            Action::SaveParams => &["ctrl+s"],
+            Action::NewTab => &["ctrl+t"],
+            Action::CloseTab => &["ctrl+d"],
+            Action::NextTab => &["ctrl+n"],
+            Action::PreviousTab => &["ctrl+p"],
+            Action::DeleteWord => &["ctrl+w"],
+            Action::TogglePassword => &["ctrl+o"],
+            Action::CopyPointer => &["y"],
+            Action::ToggleMouse => &["f2"],
+            Action::Up => &["up"],
+            Action::Down => &["down"],
+            Action::Left => &["left"],
+            Action::Right => &["right"],
+            Action::Top => &["home"],
+            Action::Bottom => &["end"],
+            Action::PageUp => &["pageup"],
+            Action::PageDown => &["pagedown"],
+        }
+    }
+
+    /// Extra keys bound with `vim = true`
+    fn vim_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Up => &["k"],
+            Action::Down => &["j"],
+            Action::Left => &["h"],
+            Action::Right => &["l"],
+            Action::Top => &["g"],
+            Action::Bottom => &["G"],
+            _ => &[],
+        }
+    }
+}
+
+/// A key with its modifiers, written like `ctrl+t`, `f1`, `esc` or `?`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // `+` on its own, or as the key after a modifier, splits into empty parts
+        if text.ends_with('+') {
+            parts.retain(|part| !part.is_empty());
+            parts.push("+");
+        }
+        let key = parts.pop().ok_or_else(|| anyhow!("Empty key binding"))?;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => bail!("Unknown modifier `{}` in key binding `{}`", other, text),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            lower => match (lower.strip_prefix('f').and_then(|n| n.parse().ok()), key.chars().count()) {
+                (Some(n), _) if (1..=12).contains(&n) => KeyCode::F(n),
+                (_, 1) => KeyCode::Char(key.chars().next().unwrap_or_default()),
+                _ => bail!("Unknown key `{}` in key binding `{}`", key, text),
+            },
+        };
+
+        // Control chars arrive lowercase whatever the shift state
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    /// Shift is part of the char itself (`G`, `?`), so it is ignored for chars
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let relevant = |modifiers: KeyModifiers| match self.code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        let code = match key.code {
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        self.code == code && relevant(self.modifiers) == relevant(key.modifiers)
+    }
+
+    /// Whether both bindings match the same key presses
+    fn overlaps(&self, other: &KeyBinding) -> bool {
+        let relevant = |modifiers: KeyModifiers| match self.code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        self.code == other.code && relevant(self.modifiers) == relevant(other.modifiers)
+    }
+
+    /// Plain chars are typed into text fields instead of triggering their action
+    pub fn is_plain_char(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && (self.modifiers - KeyModifiers::SHIFT).is_empty()
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Key bindings of every action: the defaults, replaced per action by the
+/// `[keymap]` config table, plus hjkl/gG navigation with `vim = true`
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+    pub vim: bool,
+}
+
+impl Keymap {
+    pub fn from_config(config: &KeymapConfig) -> Result<Self> {
+        for name in config.bindings.keys() {
+            if !Action::ALL.iter().any(|action| action.name() == name) {
+                bail!("Unknown action `{}` in [keymap]", name);
+            }
+        }
+
+        let mut bindings: Vec<(KeyBinding, Action)> = Vec::new();
+        for action in Action::ALL {
+            let keys: Vec<&str> = match config.bindings.get(action.name()) {
+                Some(KeyList::One(key)) => vec![key.as_str()],
+                Some(KeyList::Many(keys)) => keys.iter().map(String::as_str).collect(),
+                None => action.default_keys().to_vec(),
+            };
+            let vim_keys = if config.vim { action.vim_keys() } else { &[] };
+            for key in keys.into_iter().chain(vim_keys.iter().copied()) {
+                let binding = KeyBinding::parse(key)?;
+                let taken = bindings.iter().find(|(bound, other)| *other != action && bound.overlaps(&binding));
+                if let Some((_, other)) = taken {
+                    bail!("Key `{}` is bound to both {} and {} in [keymap]", key, other.name(), action.name());
+                }
+                bindings.push((binding, action));
+            }
+        }
+        Ok(Self { bindings, vim: config.vim })
+    }
+
+    /// Action bound to `key`. While `typing`, plain chars are left for the
+    /// focused text field.
+    pub fn action(&self, key: &KeyEvent, typing: bool) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.matches(key) && !(typing && binding.is_plain_char()))
+            .map(|(_, action)| *action)
+    }
+
+    /// Bound keys of `action` for display, e.g. `Esc/q`
+    pub fn keys(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vim: bool, bindings: &[(&str, &str)]) -> KeymapConfig {
+        KeymapConfig {
+            vim,
+            bindings: bindings
+                .iter()
+                .map(|(action, key)| (action.to_string(), KeyList::One(key.to_string())))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn edit_history_can_be_rebound() {
+        let keymap = Keymap::from_config(&config(false, &[("edit_history", "alt+e")])).unwrap();
+        let key = KeyEvent::new(KeyCode::Char('e'), KeyModifiers::ALT);
+        assert_eq!(keymap.action(&key, true), Some(Action::EditHistory));
+        let old = KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(&old, true), None);
+    }
+
+    #[test]
+    fn conflicting_bindings_are_rejected() {
+        let err = Keymap::from_config(&config(false, &[("export", "ctrl+r")])).err().unwrap();
+        assert_eq!(err.to_string(), "Key `ctrl+r` is bound to both history and export in [keymap]");
+        // Shift is part of a char, so `shift+y` is the `y` of copy_pointer
+        assert!(Keymap::from_config(&config(false, &[("export", "shift+y")])).is_err());
+        // vim keys are only taken with `vim = true`
+        assert!(Keymap::from_config(&config(true, &[("copy_pointer", "k")])).is_err());
+        assert!(Keymap::from_config(&config(false, &[("copy_pointer", "k")])).is_ok());
+        assert!(Keymap::from_config(&config(true, &[])).is_ok());
+    }
+}
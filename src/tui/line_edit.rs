@@ -0,0 +1,96 @@
+/// Cursor-aware edits of a single line of text. The cursor is a char index;
+/// `None` keeps it after the last char, which is where focus changes leave it.
+pub struct LineEdit<'a> {
+    text: &'a mut String,
+    cursor: &'a mut Option<usize>,
+}
+
+/// Char index of the cursor in `text`, clamped to its length
+pub fn cursor_position(text: &str, cursor: Option<usize>) -> usize {
+    let len = text.chars().count();
+    cursor.map_or(len, |cursor| cursor.min(len))
+}
+
+impl<'a> LineEdit<'a> {
+    pub fn new(text: &'a mut String, cursor: &'a mut Option<usize>) -> Self {
+        Self { text, cursor }
+    }
+
+    fn position(&self) -> usize {
+        cursor_position(self.text, *self.cursor)
+    }
+
+    fn byte_index(&self, position: usize) -> usize {
+        self.text.char_indices().nth(position).map_or(self.text.len(), |(i, _)| i)
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let position = self.position();
+        let index = self.byte_index(position);
+        self.text.insert(index, c);
+        *self.cursor = Some(position + 1);
+    }
+
+    /// Inserts pasted text; line breaks are dropped since fields are single line
+    pub fn insert_str(&mut self, s: &str) {
+        let s: String = s.chars().filter(|c| *c != '\n' && *c != '\r').collect();
+        let position = self.position();
+        let index = self.byte_index(position);
+        self.text.insert_str(index, &s);
+        *self.cursor = Some(position + s.chars().count());
+    }
+
+    pub fn backspace(&mut self) {
+        let position = self.position();
+        if position > 0 {
+            let index = self.byte_index(position - 1);
+            self.text.remove(index);
+            *self.cursor = Some(position - 1);
+        }
+    }
+
+    /// Deletes the char under the cursor
+    pub fn delete(&mut self) {
+        let position = self.position();
+        if position < self.text.chars().count() {
+            let index = self.byte_index(position);
+            self.text.remove(index);
+        }
+    }
+
+    /// Ctrl-W: deletes back to the start of the previous word
+    pub fn delete_word(&mut self) {
+        let position = self.position();
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = position;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        let (from, to) = (self.byte_index(start), self.byte_index(position));
+        self.text.replace_range(from..to, "");
+        *self.cursor = Some(start);
+    }
+
+    pub fn move_left(&mut self) {
+        *self.cursor = Some(self.position().saturating_sub(1));
+    }
+
+    pub fn move_right(&mut self) {
+        *self.cursor = Some((self.position() + 1).min(self.text.chars().count()));
+    }
+
+    pub fn home(&mut self) {
+        *self.cursor = Some(0);
+    }
+
+    pub fn end(&mut self) {
+        *self.cursor = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
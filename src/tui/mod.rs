@@ -1,5 +1,14 @@
 pub mod app;
+pub mod clipboard;
+pub mod export;
+pub mod fuzzy;
+pub mod history;
+pub mod json_tree;
+pub mod keymap;
+pub mod line_edit;
+pub mod param_form;
 pub mod state;
+pub mod terminal;
 pub mod ui;
 pub mod widgets;
 
@@ -0,0 +1,411 @@
+use crate::parser::{EnumVariant, ParameterMetadata, Type, TypeRegistry};
+use crate::time::{format_date, format_timestamp, now_ms, parse_date, DAY_MS};
+use serde_json::{Map, Value};
+
+/// Struct refs nested deeper than this are edited as JSON text
+const MAX_REF_DEPTH: usize = 4;
+
+/// Editor for one param value, built from its `Type`. Nested types get
+/// nested editors: struct fields, list items and the value of an optional.
+#[derive(Debug, Clone)]
+pub enum FormNode {
+    /// Free text; `ty` decides how it is picked (dates and timestamps step by day)
+    Text { ty: Type, value: String },
+    Toggle(bool),
+    Choice { variants: Vec<EnumVariant>, selected: Option<usize> },
+    /// `None` sends null, `inner_ty` builds the editor when a value is set
+    Optional { inner_ty: Type, inner: Option<Box<FormNode>> },
+    List { item_ty: Type, items: Vec<FormNode> },
+    Struct { fields: Vec<(String, FormNode)> },
+}
+
+/// One line of the param form. `path` leads from the param's root node to
+/// this row's node: field, item or optional value index at each level.
+pub struct FormRow {
+    pub param: usize,
+    pub path: Vec<usize>,
+    pub depth: usize,
+    pub label: String,
+    pub value: String,
+    /// Keys the row's editor reacts to
+    pub hint: &'static str,
+    /// Char count of the editable text `value` starts with, for text editors
+    pub text_len: Option<usize>,
+}
+
+impl FormNode {
+    /// Empty editor for a value of type `ty`
+    pub fn new(ty: &Type) -> Self {
+        Self::from_json(ty, &Value::Null)
+    }
+
+    /// Editor holding a raw param value as kept in presets and history.
+    /// Containers are stored as JSON; the comma separated form accepted by
+    /// `Type::convert_value` is read too. Enum and struct refs get the
+    /// editors of their definitions in `registry`.
+    pub fn from_raw(ty: &Type, raw: &str, registry: &TypeRegistry) -> Self {
+        Self::from_resolved_raw(&registry.resolve(ty, MAX_REF_DEPTH), raw)
+    }
+
+    fn from_resolved_raw(ty: &Type, raw: &str) -> Self {
+        match ty {
+            Type::Optional(inner) if raw.is_empty() => FormNode::Optional { inner_ty: (**inner).clone(), inner: None },
+            Type::Optional(inner) => FormNode::Optional {
+                inner_ty: (**inner).clone(),
+                inner: Some(Box::new(Self::from_resolved_raw(inner, raw))),
+            },
+            Type::Vec(_) | Type::Struct { .. } | Type::DataTable { .. } => {
+                let json = serde_json::from_str(raw).unwrap_or_else(|_| legacy_json(ty, raw));
+                Self::from_json(ty, &json)
+            }
+            _ => Self::from_json(ty, &Value::String(raw.to_string())),
+        }
+    }
+
+    fn from_json(ty: &Type, json: &Value) -> Self {
+        match ty {
+            Type::Boolean => FormNode::Toggle(json.as_bool().unwrap_or(json.as_str() == Some("true"))),
+            Type::Enum { variants, .. } => FormNode::Choice {
+                variants: variants.clone(),
+                selected: json.as_str().and_then(|name| variants.iter().position(|v| v.name == name)),
+            },
+            Type::Optional(inner) => FormNode::Optional {
+                inner_ty: (**inner).clone(),
+                inner: (!json.is_null()).then(|| Box::new(Self::from_json(inner, json))),
+            },
+            Type::Vec(item_ty) => list_node(item_ty, json),
+            Type::DataTable { name, fields } => {
+                list_node(&Type::Struct { name: name.clone(), fields: fields.clone() }, json)
+            }
+            Type::Struct { fields, .. } => FormNode::Struct {
+                fields: fields
+                    .iter()
+                    .map(|field| {
+                        let value = json.get(&field.name).unwrap_or(&Value::Null);
+                        (field.name.clone(), Self::from_json(&field.ty, value))
+                    })
+                    .collect(),
+            },
+            _ => FormNode::Text {
+                ty: ty.clone(),
+                value: match json {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                },
+            },
+        }
+    }
+
+    /// Raw value for `param_values`: scalars as typed, containers as JSON
+    pub fn to_raw(&self) -> String {
+        match self {
+            FormNode::Optional { inner: None, .. } => String::new(),
+            FormNode::Optional { inner: Some(inner), .. } => inner.to_raw(),
+            FormNode::List { .. } | FormNode::Struct { .. } => self.to_json().to_string(),
+            other => match other.to_json() {
+                Value::String(s) => s,
+                json => json.to_string(),
+            },
+        }
+    }
+
+    /// Scalars stay raw strings, `Type::convert_json` converts them when sending.
+    /// Struct fields left empty or unchosen are omitted.
+    fn to_json(&self) -> Value {
+        match self {
+            FormNode::Text { value, .. } => Value::String(value.clone()),
+            FormNode::Toggle(value) => Value::Bool(*value),
+            FormNode::Choice { variants, selected } => Value::String(
+                selected.and_then(|i| variants.get(i)).map(|v| v.name.clone()).unwrap_or_default(),
+            ),
+            FormNode::Optional { inner, .. } => inner.as_ref().map(|inner| inner.to_json()).unwrap_or(Value::Null),
+            FormNode::List { items, .. } => Value::Array(items.iter().map(FormNode::to_json).collect()),
+            FormNode::Struct { fields } => Value::Object(
+                fields
+                    .iter()
+                    .filter(|(_, node)| !node.is_unset())
+                    .map(|(name, node)| (name.clone(), node.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+
+    fn is_unset(&self) -> bool {
+        match self {
+            FormNode::Text { value, .. } => value.is_empty(),
+            FormNode::Choice { selected, .. } => selected.is_none(),
+            _ => false,
+        }
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&FormNode> {
+        let Some((&first, rest)) = path.split_first() else {
+            return Some(self);
+        };
+        let child = match self {
+            FormNode::Optional { inner, .. } if first == 0 => inner.as_deref(),
+            FormNode::List { items, .. } => items.get(first),
+            FormNode::Struct { fields } => fields.get(first).map(|(_, node)| node),
+            _ => None,
+        }?;
+        child.node(rest)
+    }
+
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut FormNode> {
+        let Some((&first, rest)) = path.split_first() else {
+            return Some(self);
+        };
+        let child = match self {
+            FormNode::Optional { inner, .. } if first == 0 => inner.as_deref_mut(),
+            FormNode::List { items, .. } => items.get_mut(first),
+            FormNode::Struct { fields } => fields.get_mut(first).map(|(_, node)| node),
+            _ => None,
+        }?;
+        child.node_mut(rest)
+    }
+
+    /// Appends the rows of this node and its children to `rows`
+    pub fn push_rows(&self, rows: &mut Vec<FormRow>, param: usize, path: Vec<usize>, depth: usize, label: String) {
+        let (value, hint) = match self {
+            FormNode::Text { ty, value } => (text_display(ty, value), text_hint(ty)),
+            FormNode::Toggle(value) => (if *value { "[x] true" } else { "[ ] false" }.to_string(), "Space toggle"),
+            FormNode::Choice { variants, selected } => {
+                let value = match selected.and_then(|i| variants.get(i)) {
+                    Some(variant) if variant.comment.is_empty() => format!("◀ {} ▶", variant.name),
+                    Some(variant) => format!("◀ {} ▶  {}", variant.name, variant.comment),
+                    None => "◀ choose ▶".to_string(),
+                };
+                (value, "←/→ choose")
+            }
+            FormNode::Optional { inner: None, .. } => ("null".to_string(), "Space set value"),
+            FormNode::Optional { inner: Some(_), .. } => ("set".to_string(), "Space set null"),
+            FormNode::List { items, .. } => (format!("{} items", items.len()), "+/Ins add item"),
+            FormNode::Struct { fields } => (format!("{} fields", fields.len()), ""),
+        };
+        let text_len = match self {
+            FormNode::Text { value, .. } => Some(value.chars().count()),
+            _ => None,
+        };
+        rows.push(FormRow { param, path: path.clone(), depth, label, value, hint, text_len });
+
+        let children: Vec<(String, &FormNode)> = match self {
+            FormNode::Optional { inner: Some(inner), .. } => vec![("value".to_string(), inner)],
+            FormNode::List { items, .. } => items.iter().enumerate().map(|(i, item)| (format!("[{}]", i), item)).collect(),
+            FormNode::Struct { fields } => fields.iter().map(|(name, node)| (name.clone(), node)).collect(),
+            _ => Vec::new(),
+        };
+        for (i, (label, child)) in children.into_iter().enumerate() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            child.push_rows(rows, param, child_path, depth + 1, label);
+        }
+    }
+
+    pub fn input(&mut self, c: char) {
+        match self {
+            FormNode::Text { ty: ty @ (Type::Date | Type::TimeStampMs), value } if c == ' ' => *value = picker_now(ty),
+            FormNode::Toggle(value) if c == ' ' => *value = !*value,
+            FormNode::Choice { .. } if c == ' ' => self.step(1),
+            FormNode::Optional { inner_ty, inner } if c == ' ' => {
+                *inner = match inner {
+                    Some(_) => None,
+                    None => Some(Box::new(FormNode::new(inner_ty))),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Text of a text editor, edited through `LineEdit`
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            FormNode::Text { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Dates and timestamps, which take Left/Right and Space as picker keys
+    pub fn is_picker(&self) -> bool {
+        matches!(self, FormNode::Text { ty: Type::Date | Type::TimeStampMs, .. })
+    }
+
+    /// Left/Right: cycles enum variants, moves dates and timestamps by a day
+    pub fn step(&mut self, step: isize) {
+        match self {
+            FormNode::Choice { variants, selected } if !variants.is_empty() => {
+                let count = variants.len() as isize;
+                let next = match *selected {
+                    Some(i) => (i as isize + step).rem_euclid(count),
+                    None if step > 0 => 0,
+                    None => count - 1,
+                };
+                *selected = Some(next as usize);
+            }
+            FormNode::Text { ty: Type::TimeStampMs, value } => {
+                let ms = value.parse::<i64>().unwrap_or(now_ms() as i64);
+                *value = (ms + step as i64 * DAY_MS).to_string();
+            }
+            FormNode::Text { ty: Type::Date, value } => {
+                let days = parse_date(value).unwrap_or(now_ms() as i64 / DAY_MS);
+                *value = format_date(days + step as i64);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A key typed on a row that isn't taking text: `+` adds a list item, Space
+/// toggles, picks the next variant or sets a date to now
+pub fn form_input(root: &mut FormNode, path: &[usize], c: char) {
+    if c == '+' {
+        insert_item(root, path);
+    } else if let Some(node) = root.node_mut(path) {
+        node.input(c);
+    }
+}
+
+/// Adds an item to the list at `path`, or after the item `path` points at
+pub fn insert_item(root: &mut FormNode, path: &[usize]) {
+    if let Some(FormNode::List { item_ty, items }) = root.node_mut(path) {
+        items.push(FormNode::new(item_ty));
+        return;
+    }
+    if let Some((&index, parent)) = path.split_last() {
+        if let Some(FormNode::List { item_ty, items }) = root.node_mut(parent) {
+            items.insert(index + 1, FormNode::new(item_ty));
+        }
+    }
+}
+
+/// Removes the list item `path` points at, if it points at one
+pub fn remove_item(root: &mut FormNode, path: &[usize]) {
+    if let Some((&index, parent)) = path.split_last() {
+        if let Some(FormNode::List { items, .. }) = root.node_mut(parent) {
+            if index < items.len() {
+                items.remove(index);
+            }
+        }
+    }
+}
+
+/// Rows of every param's form, top level labelled `name: type`
+pub fn form_rows(forms: &[FormNode], params: &[ParameterMetadata]) -> Vec<FormRow> {
+    let mut rows = Vec::new();
+    for (index, (form, param)) in forms.iter().zip(params).enumerate() {
+        form.push_rows(&mut rows, index, Vec::new(), 0, format!("{}: {}", param.name, param.ty));
+    }
+    rows
+}
+
+fn list_node(item_ty: &Type, json: &Value) -> FormNode {
+    let items = match json {
+        Value::Array(items) => items.iter().map(|item| FormNode::from_json(item_ty, item)).collect(),
+        _ => Vec::new(),
+    };
+    FormNode::List { item_ty: item_ty.clone(), items }
+}
+
+/// Reads the comma separated container format of `Type::convert_value`
+/// (`a,b` lists, `k:v,k:v` structs, `;` separated table rows) as JSON strings
+fn legacy_json(ty: &Type, raw: &str) -> Value {
+    let pairs = |row: &str| -> Value {
+        Value::Object(
+            row.split(',')
+                .filter_map(|pair| pair.split_once(':'))
+                .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                .collect(),
+        )
+    };
+    match ty {
+        _ if raw.is_empty() => Value::Null,
+        Type::Vec(_) => Value::Array(raw.split(',').map(|item| Value::String(item.to_string())).collect()),
+        Type::Struct { .. } => pairs(raw),
+        Type::DataTable { .. } => Value::Array(raw.split(';').map(pairs).collect()),
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+fn text_display(ty: &Type, value: &str) -> String {
+    match ty {
+        Type::TimeStampMs => match value.parse::<u64>() {
+            Ok(ms) => format!("{}  ({} UTC)", value, format_timestamp(ms)),
+            Err(_) => value.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+fn text_hint(ty: &Type) -> &'static str {
+    match ty {
+        Type::TimeStampMs | Type::Date => "←/→ ±1 day, Space now",
+        _ => "",
+    }
+}
+
+fn picker_now(ty: &Type) -> String {
+    match ty {
+        Type::Date => format_date(now_ms() as i64 / DAY_MS),
+        _ => now_ms().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Services;
+    use serde_json::json;
+
+    /// `Role` enum and a recursive `Node { value, role, children: Vec<Node> }`
+    fn registry() -> TypeRegistry {
+        let services: Services = serde_json::from_value(json!({
+            "enums": [{"Enum": {"name": "Role", "variants": [
+                {"name": "Admin", "value": 1, "comment": ""},
+                {"name": "User", "value": 2, "comment": ""}
+            ]}}],
+            "services": [{"name": "Trees", "id": 1, "endpoints": [{
+                "name": "SaveTree",
+                "code": 300,
+                "parameters": [{"name": "root", "ty": {"Struct": {"name": "Node", "fields": [
+                    {"name": "value", "ty": "Int"},
+                    {"name": "role", "ty": {"EnumRef": "Role"}},
+                    {"name": "children", "ty": {"Vec": {"StructRef": "Node"}}}
+                ]}}}],
+                "returns": [],
+                "stream_response": null,
+                "description": "",
+                "json_schema": null
+            }]}]
+        }))
+        .unwrap();
+        TypeRegistry::from_services(&services)
+    }
+
+    #[test]
+    fn refs_get_the_editors_of_their_definitions() {
+        let registry = registry();
+        let raw = r#"{"children":[{"children":[],"role":"User","value":"2"}],"role":"Admin","value":"1"}"#;
+        let form = FormNode::from_raw(&Type::StructRef("Node".to_string()), raw, &registry);
+
+        assert!(matches!(form.node(&[1]), Some(FormNode::Choice { selected: Some(0), .. })));
+        assert!(matches!(form.node(&[2, 0]), Some(FormNode::Struct { .. })));
+        assert!(matches!(form.node(&[2, 0, 1]), Some(FormNode::Choice { selected: Some(1), .. })));
+        let sent: Value = serde_json::from_str(&form.to_raw()).unwrap();
+        assert_eq!(sent, serde_json::from_str::<Value>(raw).unwrap());
+    }
+
+    #[test]
+    fn recursive_refs_stop_at_the_depth_limit() {
+        let registry = registry();
+        let mut form = FormNode::from_raw(&Type::StructRef("Node".to_string()), "", &registry);
+        let mut path = Vec::new();
+        for _ in 1..MAX_REF_DEPTH {
+            path.extend([2, 0]);
+            insert_item(&mut form, &path[..path.len() - 1]);
+            assert!(matches!(form.node(&path), Some(FormNode::Struct { .. })), "{:?}", path);
+        }
+        path.extend([2, 0]);
+        insert_item(&mut form, &path[..path.len() - 1]);
+        assert!(matches!(form.node(&path), Some(FormNode::Text { ty: Type::StructRef(_), .. })));
+    }
+}
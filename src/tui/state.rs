@@ -1,9 +1,35 @@
-use crate::ws::WsClient;
-use crate::parser::{EndpointMetadata, ParameterMetadata};
-use anyhow::{Context, Result};
-use std::collections::HashMap;
+use crate::ws::{login_header, response_seq, WsRequest, WsSender};
+use crate::generate::Examples;
+use crate::parser::{defaults_index, find_defaults, save_endpoint_params, EndpointDefaults, EndpointMetadata, ParameterMetadata, TypeRegistry};
+use crate::logging::LogBuffer;
+use crate::stats::{EndpointStats, FrameTiming, RequestTiming};
+use crate::tui::export::{write_export, ExportKind, ExportSource};
+use crate::tui::fuzzy::fuzzy_score;
+use crate::tui::history::{History, HistoryEntry};
+use crate::tui::json_tree::{flatten, JsonTreeState, TreeLine};
+use crate::tui::keymap::{Action, Keymap};
+use crate::tui::line_edit::LineEdit;
+use crate::tui::param_form::{form_input, form_rows, insert_item, remove_item, FormNode, FormRow};
+use anyhow::{bail, Context, Result};
+use ratatui::layout::Rect;
+use ratatui::widgets::ListState;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
-#[derive(PartialEq)]
+/// Number of stream frames kept per tab before the oldest ones are dropped
+const MAX_STREAM_FRAMES: usize = 1000;
+
+/// How long sending a request or closing the connection may take before the
+/// UI gives up on it
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines moved per mouse wheel notch in the Response block
+const WHEEL_LINES: usize = 3;
+
+#[derive(PartialEq, Clone, Copy)]
 pub enum SettingsField {
     Url,
     Username,
@@ -14,6 +40,7 @@ pub enum SettingsField {
 
 #[derive(PartialEq, Clone)]
 pub enum EndpointField {
+    Preset,
     Param(usize),
     ConnectButton,
     DisconnectButton,
@@ -28,154 +55,994 @@ pub enum AppBlock {
     EndpointsRes,
 }
 
+/// A row of the endpoint list: a service heading or an index into `AppState::endpoints`
+#[derive(PartialEq)]
+pub enum EndpointRow {
+    Service(String),
+    Endpoint(usize),
+}
+
+/// A bordered list as drawn last, with the index of its first visible row
+#[derive(Default)]
+pub struct ListArea {
+    pub area: Rect,
+    pub offset: usize,
+}
+
+impl ListArea {
+    /// Records where the list was drawn. `ListState` keeps its offset
+    /// private, so the scrolling that keeps `selected` in view is mirrored.
+    pub fn update(&mut self, area: Rect, selected: Option<usize>) {
+        let height = area.height.saturating_sub(2) as usize;
+        self.offset = match selected {
+            None => 0,
+            Some(selected) if selected < self.offset => selected,
+            Some(selected) if height > 0 && selected >= self.offset + height => selected + 1 - height,
+            Some(_) => self.offset,
+        };
+        self.area = area;
+    }
+
+    /// Index of the list row under the pointer
+    pub fn row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let inner = Rect {
+            x: self.area.x + 1,
+            y: self.area.y + 1,
+            width: self.area.width.saturating_sub(2),
+            height: self.area.height.saturating_sub(2),
+        };
+        hit(inner, column, row).then(|| self.offset + (row - inner.y) as usize)
+    }
+}
+
+/// Where the clickable parts of the screen were at the last draw
+#[derive(Default)]
+pub struct ScreenAreas {
+    pub settings_fields: Vec<(Rect, SettingsField)>,
+    pub endpoint_list: ListArea,
+    pub docs: Rect,
+    pub request: Rect,
+    /// Preset selector and buttons of the Request block
+    pub request_fields: Vec<(Rect, EndpointField)>,
+    pub params: ListArea,
+    pub response: ListArea,
+}
+
+fn hit(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
 #[derive(PartialEq)]
 pub enum JsonViewMode {
     Pretty,
     Raw,
 }
 
-pub struct AppState {
-    pub client: Option<WsClient>,
-    pub current_block: AppBlock,
-    pub focused_settings_field: Option<SettingsField>,
+impl JsonViewMode {
+    pub fn format(&self, value: &Value) -> Result<String> {
+        match self {
+            JsonViewMode::Pretty => serde_json::to_string_pretty(value).context("Failed to format JSON as pretty"),
+            JsonViewMode::Raw => serde_json::to_string(value).context("Failed to format JSON as raw"),
+        }
+    }
+}
+
+/// A request tab: its own endpoint selection, params and response buffer.
+/// All tabs send over the single connection held by `AppState`.
+pub struct RequestTab {
     pub focused_endpoint_field: Option<EndpointField>,
-    pub connected: bool,
     pub endpoint_connected: bool,
-    pub url: String,
-    pub username: String,
-    pub password: String,
     pub method_id: Option<u32>,
     pub service_name: Option<String>,
     pub params: Vec<ParameterMetadata>,
+    /// Raw value of each param, kept in sync with `param_forms`
     pub param_values: Vec<String>,
-    pub param_defaults: Vec<(String, Vec<(String, String)>)>,
+    /// Type-aware editor of each param
+    pub param_forms: Vec<FormNode>,
+    /// Keeps the params list scrolled to the focused row between draws
+    pub param_view: ListState,
+    /// Index into the endpoint's param sets, 0 being the config defaults
+    pub preset_index: usize,
     pub json_view_mode: JsonViewMode,
+    /// Text shown in the Response block: the formatted response or a status message
     pub json_data: Option<String>,
-    pub endpoints: Vec<String>,
+    /// Latest response frame, `None` while a status message is shown instead
+    pub response: Option<Value>,
+    pub tree: JsonTreeState,
+    /// Search typed after `/` in the Response block
+    pub search_query: String,
+    pub frames: VecDeque<Value>,
+    /// Last request sent from this tab, as it went over the wire
+    pub last_request: Option<Value>,
     pub selected_endpoint: usize,
-    pub endpoint_data: HashMap<String, EndpointMetadata>,
+    /// Endpoint whose params the tab holds, `None` until one is loaded
+    pub loaded_endpoint: Option<usize>,
     pub response_scroll: (u16, u16),
     pub is_stream: bool,
+    pub pending_seq: Option<u32>,
+    /// Timing of the request last sent from this tab
+    pub timing: Option<RequestTiming>,
+    /// Id of the history entry waiting for this tab's first response
+    pub history_entry: Option<usize>,
 }
 
-impl AppState {
-    pub fn new(endpoint_names: Vec<String>, endpoint_data: HashMap<String, EndpointMetadata>, param_defaults: Vec<(String, Vec<(String, String)>)>) -> Self {
+impl RequestTab {
+    pub fn new() -> Self {
         Self {
-            client: None,
-            current_block: AppBlock::Settings,
-            focused_settings_field: Some(SettingsField::Url),
             focused_endpoint_field: Some(EndpointField::Param(0)),
-            connected: false,
             endpoint_connected: false,
-            url: "ws://localhost:8443".to_string(),
-            username: String::new(),
-            password: String::new(),
             method_id: None,
             service_name: None,
             params: Vec::new(),
             param_values: Vec::new(),
-            param_defaults: param_defaults,
+            param_forms: Vec::new(),
+            param_view: ListState::default(),
+            preset_index: 0,
             json_view_mode: JsonViewMode::Pretty,
             json_data: None,
-            endpoints: endpoint_names,
+            response: None,
+            tree: JsonTreeState::default(),
+            search_query: String::new(),
+            frames: VecDeque::new(),
+            last_request: None,
             selected_endpoint: 0,
-            endpoint_data: endpoint_data,
+            loaded_endpoint: None,
             response_scroll: (0, 0),
             is_stream: false,
+            pending_seq: None,
+            timing: None,
+            history_entry: None,
+        }
+    }
+
+    /// Replaces the Response block content with a plain text message
+    pub fn show_message(&mut self, message: String) {
+        self.json_data = Some(message);
+        self.response = None;
+    }
+
+    /// Rebuilds the param editors from `param_values`
+    pub fn rebuild_forms(&mut self, registry: &TypeRegistry) {
+        self.param_forms = self.params
+            .iter()
+            .zip(self.param_values.iter())
+            .map(|(param, raw)| FormNode::from_raw(&param.ty, raw, registry))
+            .collect();
+    }
+
+    pub fn form_rows(&self) -> Vec<FormRow> {
+        form_rows(&self.param_forms, &self.params)
+    }
+
+    /// Looks at the form node of the focused param row
+    fn focused_node<R>(&self, look: impl FnOnce(&FormNode) -> R) -> Option<R> {
+        let Some(EndpointField::Param(index)) = self.focused_endpoint_field else {
+            return None;
+        };
+        let rows = self.form_rows();
+        let row = rows.get(index)?;
+        self.param_forms.get(row.param)?.node(&row.path).map(look)
+    }
+
+    /// Applies `edit` to the param form owning the focused row, with the
+    /// row's path, then stores the param's new raw value
+    fn edit_focused_param(&mut self, edit: impl FnOnce(&mut FormNode, &[usize])) {
+        let Some(EndpointField::Param(index)) = self.focused_endpoint_field else {
+            return;
+        };
+        let rows = self.form_rows();
+        let Some(row) = rows.get(index) else {
+            return;
+        };
+        let Some(form) = self.param_forms.get_mut(row.param) else {
+            return;
+        };
+        edit(form, &row.path);
+        self.param_values[row.param] = form.to_raw();
+
+        // Removing items can leave the focus past the last row
+        let row_count = self.form_rows().len();
+        if index >= row_count && row_count > 0 {
+            self.focused_endpoint_field = Some(EndpointField::Param(row_count - 1));
+        }
+    }
+
+    /// Lines of the response tree, when the response is shown as one
+    pub fn tree_lines(&self) -> Option<Vec<TreeLine>> {
+        match (&self.response, &self.json_view_mode) {
+            (Some(response), JsonViewMode::Pretty) => Some(flatten(response, &self.tree.collapsed)),
+            _ => None,
+        }
+    }
+
+    /// Text of every line in the Response block, in display order
+    fn response_lines(&self) -> Vec<String> {
+        match self.tree_lines() {
+            Some(lines) => lines.iter().map(TreeLine::text).collect(),
+            None => self.json_data.as_deref().unwrap_or("").lines().map(str::to_string).collect(),
+        }
+    }
+
+    /// Indices of the response lines containing the search query
+    pub fn search_matches(&self) -> Vec<usize> {
+        if self.search_query.is_empty() {
+            return Vec::new();
+        }
+        let needle = self.search_query.to_ascii_lowercase();
+        self.response_lines()
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.to_ascii_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The selected tree line, or the first visible line of plain text
+    pub fn current_line(&self) -> usize {
+        match self.tree_lines() {
+            Some(_) => self.tree.selected,
+            None => self.response_scroll.0 as usize,
+        }
+    }
+
+    fn jump_to_line(&mut self, line: usize) {
+        let last = self.response_lines().len().saturating_sub(1);
+        let line = line.min(last);
+        match self.tree_lines() {
+            Some(_) => self.tree.selected = line,
+            None => self.response_scroll.0 = u16::try_from(line).unwrap_or(u16::MAX),
+        }
+    }
+
+    fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.search_matches();
+        let current = self.current_line();
+        let target = if forward {
+            matches.iter().find(|&&line| line > current).or(matches.first())
+        } else {
+            matches.iter().rev().find(|&&line| line < current).or(matches.last())
+        };
+        if let Some(&line) = target {
+            self.jump_to_line(line);
+        }
+    }
+
+    fn receive_frame(&mut self, frame: Value) {
+        self.json_data = Some(match self.json_view_mode.format(&frame) {
+            Ok(formatted_json) => formatted_json,
+            Err(err) => format!("Error: {}", err),
+        });
+        self.response = Some(frame.clone());
+
+        if self.frames.len() == MAX_STREAM_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+
+        if !self.is_stream {
+            // A plain request is done after its single response
+            self.pending_seq = None;
         }
     }
+}
 
-    // Scroll logic for the response section
+/// A connection for the UI loop to open off its own task, see `handle_connect`
+pub struct ConnectRequest {
+    pub url: String,
+    pub header: String,
+}
+
+pub struct AppState {
+    pub client: Option<WsSender>,
+    /// Set by the Connect button, taken by the UI loop
+    pub connect_request: Option<ConnectRequest>,
+    /// A connect is running and its outcome not yet delivered
+    pub connecting: bool,
+    pub connection_id: u64,
+    pub current_block: AppBlock,
+    pub focused_settings_field: Option<SettingsField>,
+    pub connected: bool,
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// Whether the password is drawn in plain text instead of masked
+    pub password_visible: bool,
+    /// Cursor of the focused text field, see `LineEdit`
+    pub text_cursor: Option<usize>,
+    pub param_defaults: Vec<EndpointDefaults>,
+    pub endpoints: Vec<String>,
+    pub endpoint_data: HashMap<String, EndpointMetadata>,
+    /// Named types of services.json, for example values of struct and enum refs
+    pub registry: TypeRegistry,
+    /// Fuzzy search typed into the endpoint list
+    pub endpoint_filter: String,
+    /// With vim navigation the filter is typed after `/` instead of right away
+    pub endpoint_filter_editing: bool,
+    pub endpoint_list_state: ListState,
+    pub docs_scroll: u16,
+    pub tabs: Vec<RequestTab>,
+    pub active_tab: usize,
+    pub history: History,
+    pub history_open: bool,
+    pub history_query: String,
+    pub history_selected: usize,
+    /// One-off message shown in place of the help line until the next key press
+    pub status_message: Option<String>,
+    /// Whether keys currently go to the Response block search query
+    pub search_editing: bool,
+    /// Inner height of the Response block at the last draw, for paging
+    pub response_height: u16,
+    pub export_open: bool,
+    pub export_selected: usize,
+    pub config_path: String,
+    /// Preset name being typed for "save params", empty saves the defaults
+    pub save_prompt: Option<String>,
+    pub keymap: Keymap,
+    pub help_open: bool,
+    /// Asking whether to quit while streams are still running
+    pub quit_confirm: bool,
+    pub areas: ScreenAreas,
+    /// Off while the mouse is released for the terminal's own text selection
+    pub mouse_captured: bool,
+    /// Recent log lines, shown in the log pane while `logs_open`
+    pub logs: LogBuffer,
+    pub logs_open: bool,
+    /// Latency of every response received this session, per endpoint
+    pub stats: EndpointStats,
+    pub stats_open: bool,
+}
+
+impl AppState {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(endpoint_names: Vec<String>, endpoint_data: HashMap<String, EndpointMetadata>, registry: TypeRegistry, param_defaults: Vec<EndpointDefaults>, history: History, config_path: String, keymap: Keymap, logs: LogBuffer) -> Self {
+        Self {
+            client: None,
+            connect_request: None,
+            connecting: false,
+            connection_id: 0,
+            current_block: AppBlock::Settings,
+            focused_settings_field: Some(SettingsField::Url),
+            connected: false,
+            url: "ws://localhost:8443".to_string(),
+            username: String::new(),
+            password: String::new(),
+            password_visible: false,
+            text_cursor: None,
+            param_defaults,
+            endpoints: endpoint_names,
+            endpoint_data,
+            registry,
+            endpoint_filter: String::new(),
+            endpoint_filter_editing: false,
+            endpoint_list_state: ListState::default(),
+            docs_scroll: 0,
+            tabs: vec![RequestTab::new()],
+            active_tab: 0,
+            history,
+            history_open: false,
+            history_query: String::new(),
+            history_selected: 0,
+            status_message: None,
+            search_editing: false,
+            response_height: 0,
+            export_open: false,
+            export_selected: 0,
+            config_path,
+            save_prompt: None,
+            keymap,
+            help_open: false,
+            quit_confirm: false,
+            areas: ScreenAreas::default(),
+            mouse_captured: true,
+            logs,
+            logs_open: false,
+            stats: EndpointStats::default(),
+            stats_open: false,
+        }
+    }
+
+    // Key bindings
+    /// Whether plain chars currently go into a text field or filter, which
+    /// keeps them from triggering their key bindings
+    pub fn typing(&self) -> bool {
+        match self.current_block {
+            AppBlock::Settings => matches!(
+                self.focused_settings_field,
+                Some(SettingsField::Url | SettingsField::Username | SettingsField::Password)
+            ),
+            AppBlock::EndpointList => !self.keymap.vim || self.endpoint_filter_editing,
+            AppBlock::EndpointsReq => self.tab().focused_node(|node| matches!(node, FormNode::Text { .. })) == Some(true),
+            AppBlock::EndpointsRes => false,
+        }
+    }
+
+    /// Number of tabs with a stream subscription still running
+    pub fn active_streams(&self) -> usize {
+        self.tabs.iter().filter(|tab| tab.is_stream && tab.pending_seq.is_some()).count()
+    }
+
+    /// Returns whether the app can quit right away; with streams running
+    /// the quit confirmation is opened instead
+    pub fn request_quit(&mut self) -> bool {
+        if self.active_streams() == 0 {
+            return true;
+        }
+        self.quit_confirm = true;
+        false
+    }
+
+    /// Runs a bound action. Quitting, copying to the clipboard and mouse
+    /// capture are left to the event loop, which owns the terminal.
+    pub fn perform(&mut self, action: Action) {
+        match action {
+            Action::Quit | Action::CopyPointer | Action::ToggleMouse | Action::EditHistory => {}
+            Action::Help => self.help_open = !self.help_open,
+            Action::Logs => self.logs_open = !self.logs_open,
+            Action::Stats => self.stats_open = !self.stats_open,
+            Action::NextBlock => self.switch_block(),
+            Action::History => self.toggle_history(),
+            Action::Export => self.toggle_export(),
+            Action::SaveParams => self.open_save_prompt(),
+            Action::NewTab => self.new_tab(),
+            Action::CloseTab => self.close_tab(),
+            Action::NextTab => self.next_tab(),
+            Action::PreviousTab => self.previous_tab(),
+            Action::DeleteWord => self.delete_word(),
+            Action::TogglePassword => self.toggle_password_visible(),
+            Action::Up => self.previous_field(),
+            Action::Down => self.next_field(),
+            Action::Left => self.move_left(),
+            Action::Right => self.move_right(),
+            Action::Top if self.current_block == AppBlock::EndpointsRes => self.response_home(),
+            Action::Top => self.text_home(),
+            Action::Bottom if self.current_block == AppBlock::EndpointsRes => self.response_end(),
+            Action::Bottom => self.text_end(),
+            Action::PageUp => self.page_up(),
+            Action::PageDown => self.page_down(),
+        }
+    }
+
+    pub fn tab(&self) -> &RequestTab {
+        &self.tabs[self.active_tab]
+    }
+
+    pub fn tab_mut(&mut self) -> &mut RequestTab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    /// Label shown in the tab bar: the selected endpoint once one has been picked
+    pub fn tab_title(&self, index: usize) -> String {
+        let tab = &self.tabs[index];
+        match tab.method_id {
+            Some(_) => self.endpoints.get(tab.selected_endpoint).cloned().unwrap_or_default(),
+            None => format!("Tab {}", index + 1),
+        }
+    }
+
+    // Tab management
+    pub fn new_tab(&mut self) {
+        self.text_cursor = None;
+        self.tabs.push(RequestTab::new());
+        self.active_tab = self.tabs.len() - 1;
+        if self.connected {
+            self.update_selected_endpoint_data();
+        }
+    }
+
+    pub fn close_tab(&mut self) {
+        self.text_cursor = None;
+        if self.tabs.len() > 1 {
+            self.tabs.remove(self.active_tab);
+            if self.active_tab >= self.tabs.len() {
+                self.active_tab = self.tabs.len() - 1;
+            }
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.text_cursor = None;
+        self.active_tab = (self.active_tab + 1) % self.tabs.len();
+    }
+
+    pub fn previous_tab(&mut self) {
+        self.text_cursor = None;
+        self.active_tab = (self.active_tab + self.tabs.len() - 1) % self.tabs.len();
+    }
+
+    /// Routes a frame from the shared connection to the tab whose request it answers.
+    /// Frames of requests no tab waits for any more, like a stream the tab has
+    /// since replaced with a new request, are dropped.
+    pub fn dispatch_frame(&mut self, frame: Value) {
+        let seq = response_seq(&frame);
+        let Some(index) = seq.and_then(|seq| self.tabs.iter().position(|tab| tab.pending_seq == Some(seq))) else {
+            debug!("Dropping frame for seq {:?}, no tab is waiting for it", seq);
+            return;
+        };
+
+        let tab = &mut self.tabs[index];
+        if let Some(timing) = tab.timing.as_mut() {
+            match timing.frame() {
+                FrameTiming::First(ms) => self.stats.record_latency(&timing.endpoint, ms),
+                FrameTiming::Interval(ms) => self.stats.record_interval(&timing.endpoint, ms),
+            }
+        }
+        let latency = tab.timing.as_ref().and_then(|timing| timing.first_frame);
+        if let (Some(entry), Some(latency)) = (tab.history_entry.take(), latency) {
+            let latency_ms = latency.as_millis() as u64;
+            if let Some(entry) = self.history.get_mut(entry) {
+                entry.complete(&frame, latency_ms);
+            }
+        }
+
+        self.tabs[index].receive_frame(frame);
+    }
+
+    // Export menu
+    pub fn toggle_export(&mut self) {
+        self.export_open = !self.export_open;
+        self.export_selected = 0;
+    }
+
+    pub fn export_next(&mut self) {
+        if self.export_selected + 1 < ExportKind::ALL.len() {
+            self.export_selected += 1;
+        }
+    }
+
+    pub fn export_previous(&mut self) {
+        self.export_selected = self.export_selected.saturating_sub(1);
+    }
+
+    /// Writes the selected export of the active tab into the working directory
+    pub fn export_selected_kind(&mut self) {
+        let kind = ExportKind::ALL[self.export_selected];
+        let tab = self.tab();
+        let endpoint = match tab.method_id {
+            Some(_) => self.endpoints.get(tab.selected_endpoint).map(String::as_str).unwrap_or_default(),
+            None => "",
+        };
+        let source = ExportSource {
+            endpoint,
+            response: tab.response.as_ref(),
+            request: tab.last_request.as_ref(),
+            frames: &tab.frames,
+            url: &self.url,
+            username: &self.username,
+        };
+        self.status_message = Some(match write_export(kind, &source, Path::new(".")) {
+            Ok(path) => format!("Exported to {}", path.display()),
+            Err(err) => format!("Export failed: {}", err),
+        });
+        self.export_open = false;
+    }
+
+    // Saving params to the config file
+    pub fn open_save_prompt(&mut self) {
+        if self.tab().method_id.is_some() {
+            self.save_prompt = Some(String::new());
+        }
+    }
+
+    /// Saves the active tab's params into the config file, as the endpoint
+    /// defaults or under the preset name typed into the prompt
+    pub fn save_params(&mut self) {
+        let Some(preset) = self.save_prompt.take() else {
+            return;
+        };
+        let tab = self.tab();
+        let Some(endpoint) = self.endpoints.get(tab.selected_endpoint).filter(|_| tab.method_id.is_some()) else {
+            return;
+        };
+        let Some(metadata) = self.endpoint_data.get(endpoint) else {
+            return;
+        };
+        let params: Vec<_> = tab.params
+            .iter()
+            .zip(tab.param_values.iter())
+            .map(|(param, value)| (param.name.clone(), param.ty.clone(), value.clone()))
+            .collect();
+        let preset = Some(preset.trim()).filter(|preset| !preset.is_empty());
+
+        if let Err(err) = save_endpoint_params(&self.config_path, endpoint, metadata, preset, &params) {
+            self.status_message = Some(format!("Failed to save params: {}", err));
+            return;
+        }
+
+        // Later selections of this endpoint pick up the saved values
+        let values: Vec<(String, String)> = params
+            .into_iter()
+            .filter(|(_, _, value)| !value.is_empty())
+            .map(|(name, _, value)| (name, value))
+            .collect();
+        let index = match defaults_index(&self.param_defaults, endpoint, metadata) {
+            Some(index) => index,
+            None => {
+                self.param_defaults.push(EndpointDefaults {
+                    key: metadata.method_id.to_string(),
+                    name: endpoint.clone(),
+                    params: Vec::new(),
+                    presets: Vec::new(),
+                });
+                self.param_defaults.len() - 1
+            }
+        };
+        let defaults = &mut self.param_defaults[index];
+        match preset {
+            Some(preset) => match defaults.presets.iter_mut().find(|(name, _)| name == preset) {
+                Some(existing) => existing.1 = values,
+                None => defaults.presets.push((preset.to_string(), values)),
+            },
+            None => defaults.params = values,
+        }
+
+        self.status_message = Some(match preset {
+            Some(preset) => format!("Saved preset \"{}\" to {}", preset, self.config_path),
+            None => format!("Saved default params to {}", self.config_path),
+        });
+    }
+
+    // History panel
+    pub fn toggle_history(&mut self) {
+        self.history_open = !self.history_open;
+        self.history_selected = 0;
+    }
+
+    /// History entries matching the search query, newest first
+    pub fn history_matches(&self) -> Vec<usize> {
+        self.history.search(&self.history_query)
+    }
+
+    pub fn history_next(&mut self) {
+        if self.history_selected + 1 < self.history_matches().len() {
+            self.history_selected += 1;
+        }
+    }
+
+    pub fn history_previous(&mut self) {
+        self.history_selected = self.history_selected.saturating_sub(1);
+    }
+
+    pub fn history_input(&mut self, c: char) {
+        self.history_query.push(c);
+        self.history_selected = 0;
+    }
+
+    pub fn history_delete_last_char(&mut self) {
+        self.history_query.pop();
+        self.history_selected = 0;
+    }
+
+    /// Loads the selected history entry into the active tab. With `send` the
+    /// request goes out immediately, otherwise the Request block is focused
+    /// so the params can be edited first.
+    pub async fn rerun_history(&mut self, send: bool) -> Result<()> {
+        let Some(&entry) = self.history_matches().get(self.history_selected) else {
+            return Ok(());
+        };
+        let entry = self.history.entries[entry].clone();
+        let selected = self.endpoints
+            .iter()
+            .position(|name| *name == entry.endpoint)
+            .with_context(|| format!("Endpoint {} is not in services.json", entry.endpoint))?;
+
+        self.tab_mut().selected_endpoint = selected;
+        self.update_selected_endpoint_data();
+        let tab = &mut self.tabs[self.active_tab];
+        for (param, value) in tab.params.iter().zip(tab.param_values.iter_mut()) {
+            if let Some((_, saved)) = entry.params.iter().find(|(name, _)| *name == param.name) {
+                *value = saved.clone();
+            }
+        }
+        tab.rebuild_forms(&self.registry);
+        self.history_open = false;
+
+        if send {
+            self.handle_endpoint_connect().await
+        } else {
+            self.current_block = AppBlock::EndpointsReq;
+            Ok(())
+        }
+    }
+
+    pub fn connection_lost(&mut self, err: anyhow::Error) {
+        self.client = None;
+        self.connected = false;
+        for tab in &mut self.tabs {
+            tab.endpoint_connected = false;
+            tab.pending_seq = None;
+        }
+        self.tab_mut().show_message(format!("Error receiving data: {:?}", err));
+    }
+
+    /// Metadata of the endpoint selected in the active tab
+    pub fn selected_metadata(&self) -> Option<&EndpointMetadata> {
+        self.endpoints
+            .get(self.tab().selected_endpoint)
+            .and_then(|endpoint| self.endpoint_data.get(endpoint))
+    }
+
+    pub fn page_down(&mut self) {
+        match self.current_block {
+            AppBlock::EndpointList => self.docs_scroll = self.docs_scroll.saturating_add(5),
+            AppBlock::EndpointsRes => self.response_page_down(),
+            _ => {}
+        }
+    }
+
+    pub fn page_up(&mut self) {
+        match self.current_block {
+            AppBlock::EndpointList => self.docs_scroll = self.docs_scroll.saturating_sub(5),
+            AppBlock::EndpointsRes => self.response_page_up(),
+            _ => {}
+        }
+    }
+
+    // Scroll logic for the response section. A JSON response is shown as a
+    // tree where Up/Down move the selection and Left/Right collapse/expand.
     pub fn scroll_response_down(&mut self) {
-        self.response_scroll.0 += 1;
+        let tab = self.tab_mut();
+        match tab.tree_lines() {
+            Some(lines) => tab.tree.select_next(lines.len()),
+            None => tab.response_scroll.0 += 1,
+        }
     }
 
     pub fn scroll_response_up(&mut self) {
-        if self.response_scroll.0 > 0 {
-            self.response_scroll.0 -= 1;
+        let tab = self.tab_mut();
+        match tab.tree_lines() {
+            Some(_) => tab.tree.select_previous(),
+            None => tab.response_scroll.0 = tab.response_scroll.0.saturating_sub(1),
         }
     }
 
     pub fn scroll_response_right(&mut self) {
         if self.current_block == AppBlock::EndpointsRes {
-            self.response_scroll.1 += 1;
+            let tab = self.tab_mut();
+            match tab.tree_lines() {
+                Some(lines) => tab.tree.expand(&lines),
+                None => tab.response_scroll.1 += 1,
+            }
         }
     }
 
     pub fn scroll_response_left(&mut self) {
         if self.current_block == AppBlock::EndpointsRes {
-            if self.response_scroll.1 > 0 {
-                self.response_scroll.1 -= 1;
+            let tab = self.tab_mut();
+            match tab.tree_lines() {
+                Some(lines) => tab.tree.collapse(&lines),
+                None => tab.response_scroll.1 = tab.response_scroll.1.saturating_sub(1),
             }
         }
     }
 
+    // Response search and paging
+    pub fn start_search(&mut self) {
+        self.search_editing = true;
+        self.tab_mut().search_query.clear();
+    }
+
+    pub fn search_input(&mut self, c: char) {
+        self.tab_mut().search_query.push(c);
+    }
+
+    pub fn search_delete_last_char(&mut self) {
+        self.tab_mut().search_query.pop();
+    }
+
+    /// Ends query editing and jumps to the first match after the current line
+    pub fn finish_search(&mut self) {
+        self.search_editing = false;
+        let tab = self.tab_mut();
+        if !tab.search_matches().contains(&tab.current_line()) {
+            tab.jump_to_match(true);
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search_editing = false;
+        self.tab_mut().search_query.clear();
+    }
+
+    pub fn next_match(&mut self) {
+        self.tab_mut().jump_to_match(true);
+    }
+
+    pub fn previous_match(&mut self) {
+        self.tab_mut().jump_to_match(false);
+    }
+
+    pub fn response_page_down(&mut self) {
+        let page = self.response_height.max(1) as usize;
+        let tab = self.tab_mut();
+        tab.jump_to_line(tab.current_line() + page);
+    }
+
+    pub fn response_page_up(&mut self) {
+        let page = self.response_height.max(1) as usize;
+        let tab = self.tab_mut();
+        tab.jump_to_line(tab.current_line().saturating_sub(page));
+    }
+
+    pub fn response_home(&mut self) {
+        self.tab_mut().jump_to_line(0);
+    }
+
+    pub fn response_end(&mut self) {
+        self.tab_mut().jump_to_line(usize::MAX);
+    }
+
+    /// Keys typed into the Response block: `/` starts a search, `n`/`N` jump
+    /// to the next/previous match
+    pub fn response_input(&mut self, c: char) {
+        match c {
+            '/' => self.start_search(),
+            'n' => self.next_match(),
+            'N' => self.previous_match(),
+            _ => {}
+        }
+    }
+
+    pub fn toggle_response_node(&mut self) {
+        let tab = self.tab_mut();
+        if let Some(lines) = tab.tree_lines() {
+            tab.tree.toggle(&lines);
+        }
+    }
+
+    /// JSON pointer of the node selected in the response tree
+    pub fn selected_pointer(&self) -> Option<String> {
+        let tab = self.tab();
+        let lines = tab.tree_lines()?;
+        tab.tree.selected_pointer(&lines).map(str::to_string)
+    }
+
     // Handle user input
     pub fn update_input(&mut self, c: char) {
         match self.current_block {
             AppBlock::Settings => self.update_settings_input(c),
+            AppBlock::EndpointList if self.keymap.vim && !self.endpoint_filter_editing => {
+                self.endpoint_filter_editing = c == '/';
+            }
+            AppBlock::EndpointList => {
+                self.endpoint_filter.push(c);
+                self.select_first_visible_endpoint();
+            }
             AppBlock::EndpointsReq => self.update_endpoint_input(c),
-            _ => {}
+            AppBlock::EndpointsRes => self.response_input(c),
         }
     }
 
     fn update_settings_input(&mut self, c: char) {
-        match self.focused_settings_field {
-            Some(SettingsField::Url) => self.url.push(c),
-            Some(SettingsField::Username) => self.username.push(c),
-            Some(SettingsField::Password) => self.password.push(c),
-            _ => {}
-        }
+        self.edit_focused_text(|line| line.insert_char(c));
     }
 
     fn update_endpoint_input(&mut self, c: char) {
-        if let Some(EndpointField::Param(index)) = self.focused_endpoint_field {
-            if let Some(param) = self.param_values.get_mut(index) {
-                param.push(c);
+        let takes_text = self.tab().focused_node(|node| matches!(node, FormNode::Text { .. }) && !(c == ' ' && node.is_picker()));
+        if !(takes_text == Some(true) && self.edit_focused_text(|line| line.insert_char(c))) {
+            self.tab_mut().edit_focused_param(|form, path| form_input(form, path, c));
+        }
+    }
+
+    /// Runs `edit` on the focused URL, credential or param text field.
+    /// Returns false when no text field has the focus.
+    fn edit_focused_text(&mut self, edit: impl FnOnce(&mut LineEdit)) -> bool {
+        let cursor = &mut self.text_cursor;
+        match self.current_block {
+            AppBlock::Settings => {
+                let text = match self.focused_settings_field {
+                    Some(SettingsField::Url) => &mut self.url,
+                    Some(SettingsField::Username) => &mut self.username,
+                    Some(SettingsField::Password) => &mut self.password,
+                    _ => return false,
+                };
+                edit(&mut LineEdit::new(text, cursor));
+                true
             }
+            AppBlock::EndpointsReq => {
+                let mut edited = false;
+                self.tabs[self.active_tab].edit_focused_param(|form, path| {
+                    if let Some(text) = form.node_mut(path).and_then(FormNode::text_mut) {
+                        edit(&mut LineEdit::new(text, cursor));
+                        edited = true;
+                    }
+                });
+                edited
+            }
+            _ => false,
         }
     }
 
+    /// Bracketed paste: inserted at the cursor of the focused text field,
+    /// or typed into the endpoint filter or an open prompt
+    pub fn paste(&mut self, text: &str) {
+        if self.history_open {
+            self.history_query.push_str(text);
+        } else if let Some(prompt) = &mut self.save_prompt {
+            prompt.push_str(text);
+        } else if self.search_editing {
+            self.tab_mut().search_query.push_str(text);
+        } else if self.current_block == AppBlock::EndpointList {
+            self.endpoint_filter.push_str(text.trim());
+            self.select_first_visible_endpoint();
+        } else {
+            self.edit_focused_text(|line| line.insert_str(text));
+        }
+    }
+
+    /// Ctrl-W in a text field
+    pub fn delete_word(&mut self) {
+        self.edit_focused_text(|line| line.delete_word());
+    }
+
+    pub fn text_home(&mut self) {
+        self.edit_focused_text(|line| line.home());
+    }
+
+    pub fn text_end(&mut self) {
+        self.edit_focused_text(|line| line.end());
+    }
+
+    pub fn toggle_password_visible(&mut self) {
+        self.password_visible = !self.password_visible;
+    }
+
     pub fn delete_last_char(&mut self) {
         match self.current_block {
             AppBlock::Settings => self.delete_last_char_from_settings(),
+            AppBlock::EndpointList => {
+                self.endpoint_filter.pop();
+                self.select_first_visible_endpoint();
+            }
             AppBlock::EndpointsReq => self.delete_last_char_from_endpoint(),
             _ => {}
         }
     }
 
     fn delete_last_char_from_settings(&mut self) {
-        match self.focused_settings_field {
-            Some(SettingsField::Url) => { self.url.pop(); },
-            Some(SettingsField::Username) => { self.username.pop(); },
-            Some(SettingsField::Password) => { self.password.pop(); },
-            _ => {}
-        }
+        self.edit_focused_text(|line| line.backspace());
     }
 
     fn delete_last_char_from_endpoint(&mut self) {
-        if let Some(EndpointField::Param(index)) = self.focused_endpoint_field {
-            if let Some(param) = self.param_values.get_mut(index) {
-                param.pop();
-            }
+        self.edit_focused_text(|line| line.backspace());
+    }
+
+    /// Insert in the Request block: adds an item to the focused list
+    pub fn insert_param_item(&mut self) {
+        if self.current_block == AppBlock::EndpointsReq {
+            self.tab_mut().edit_focused_param(insert_item);
+        }
+    }
+
+    /// Delete: removes the char under the cursor. In the Request block an
+    /// empty text field or any other row removes the list item it is in.
+    pub fn delete_forward(&mut self) {
+        let mut deleted = false;
+        self.edit_focused_text(|line| {
+            deleted = !line.is_empty();
+            line.delete();
+        });
+        if !deleted && self.current_block == AppBlock::EndpointsReq {
+            self.tab_mut().edit_focused_param(remove_item);
         }
     }
 
     // Navigation within blocks
     pub fn next_field(&mut self) {
+        self.text_cursor = None;
         match self.current_block {
             AppBlock::Settings => self.focused_settings_field = self.next_settings_field(),
             AppBlock::EndpointList => self.select_next_endpoint(),
-            AppBlock::EndpointsReq => self.focused_endpoint_field = self.next_endpoint_field(),
+            AppBlock::EndpointsReq => self.tab_mut().focused_endpoint_field = self.next_endpoint_field(),
             AppBlock::EndpointsRes => self.scroll_response_down(),
         }
     }
 
     pub fn previous_field(&mut self) {
+        self.text_cursor = None;
         match self.current_block {
             AppBlock::Settings => self.focused_settings_field = self.previous_settings_field(),
             AppBlock::EndpointList => self.select_previous_endpoint(),
-            AppBlock::EndpointsReq => self.focused_endpoint_field = self.previous_endpoint_field(),
+            AppBlock::EndpointsReq => self.tab_mut().focused_endpoint_field = self.previous_endpoint_field(),
             AppBlock::EndpointsRes => self.scroll_response_up(),
         }
     }
@@ -203,51 +1070,50 @@ impl AppState {
     }
 
     fn next_endpoint_field(&self) -> Option<EndpointField> {
-        match self.focused_endpoint_field {
-            Some(EndpointField::Param(index)) if index + 1 < self.params.len() => {
+        let tab = self.tab();
+        match tab.focused_endpoint_field {
+            Some(EndpointField::Preset) if tab.params.is_empty() => Some(EndpointField::ConnectButton),
+            Some(EndpointField::Preset) => Some(EndpointField::Param(0)),
+            Some(EndpointField::Param(index)) if index + 1 < tab.form_rows().len() => {
                 Some(EndpointField::Param(index + 1))
             }
             Some(EndpointField::Param(_)) => Some(EndpointField::ConnectButton),
             Some(EndpointField::ConnectButton) => Some(EndpointField::DisconnectButton),
             Some(EndpointField::DisconnectButton) => Some(EndpointField::JsonToggleButton),
-            Some(EndpointField::JsonToggleButton) | None => {
-                if self.params.is_empty() {
-                    Some(EndpointField::ConnectButton)
-                } else {
-                    Some(EndpointField::Param(0))
-                }
-            }
+            Some(EndpointField::JsonToggleButton) | None => Some(EndpointField::Preset),
         }
     }
-    
+
     fn previous_endpoint_field(&self) -> Option<EndpointField> {
-        match self.focused_endpoint_field {
+        let tab = self.tab();
+        match tab.focused_endpoint_field {
             Some(EndpointField::Param(index)) if index > 0 => {
                 Some(EndpointField::Param(index - 1))
             }
-            Some(EndpointField::Param(_)) => Some(EndpointField::JsonToggleButton),
+            Some(EndpointField::Param(_)) => Some(EndpointField::Preset),
+            Some(EndpointField::Preset) | None => Some(EndpointField::JsonToggleButton),
             Some(EndpointField::JsonToggleButton) => Some(EndpointField::DisconnectButton),
             Some(EndpointField::DisconnectButton) => Some(EndpointField::ConnectButton),
-            Some(EndpointField::ConnectButton) | None => {
-                if self.params.is_empty() {
-                    Some(EndpointField::JsonToggleButton)
-                } else {
-                    Some(EndpointField::Param(self.params.len() - 1))
+            Some(EndpointField::ConnectButton) => {
+                match tab.form_rows().len() {
+                    0 => Some(EndpointField::Preset),
+                    row_count => Some(EndpointField::Param(row_count - 1)),
                 }
             }
         }
-    }    
-    
+    }
+
     // Block switching
     pub fn switch_block(&mut self) {
+        self.text_cursor = None;
         if self.connected {
             self.current_block = match self.current_block {
                 AppBlock::Settings => {
-                    self.update_selected_endpoint_data();
-                    AppBlock::EndpointList 
+                    self.load_selected_endpoint();
+                    AppBlock::EndpointList
                 },
                 AppBlock::EndpointList => {
-                    self.update_selected_endpoint_data();
+                    self.load_selected_endpoint();
                     AppBlock::EndpointsReq
                 }
                 AppBlock::EndpointsReq => AppBlock::EndpointsRes,
@@ -256,195 +1122,610 @@ impl AppState {
         }
     }
 
-    // Endpoint selection
-    pub fn select_next_endpoint(&mut self) {
-        if self.connected {
-            if self.selected_endpoint < self.endpoints.len() - 1 {
-                self.selected_endpoint += 1;
+    // Mouse
+    /// Whether a popup is drawn over the blocks, which then ignore the mouse
+    pub fn popup_open(&self) -> bool {
+        self.quit_confirm || self.help_open || self.stats_open || self.history_open || self.export_open || self.save_prompt.is_some()
+    }
+
+    /// Focuses the block and field under a left click. Returns whether a
+    /// button was clicked, which the caller then presses like Enter.
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        if let Some(&(_, field)) = self.areas.settings_fields.iter().find(|(area, _)| hit(*area, column, row)) {
+            self.focus_block(AppBlock::Settings);
+            self.focused_settings_field = Some(field);
+            return matches!(field, SettingsField::ConnectButton | SettingsField::DisconnectButton);
+        }
+        // Like Tab, only Settings can be focused before connecting
+        if !self.connected {
+            return false;
+        }
+
+        if hit(self.areas.endpoint_list.area, column, row) {
+            self.focus_block(AppBlock::EndpointList);
+            let index = self.areas.endpoint_list.row_at(column, row);
+            if let Some(EndpointRow::Endpoint(index)) = index.and_then(|index| self.endpoint_rows().into_iter().nth(index)) {
+                self.tab_mut().selected_endpoint = index;
                 self.update_selected_endpoint_data();
             }
+        } else if hit(self.areas.docs, column, row) {
+            self.focus_block(AppBlock::EndpointList);
+        } else if let Some((_, field)) = self.areas.request_fields.iter().find(|(area, _)| hit(*area, column, row)) {
+            let field = field.clone();
+            self.focus_block(AppBlock::EndpointsReq);
+            let is_button = field != EndpointField::Preset;
+            self.tab_mut().focused_endpoint_field = Some(field);
+            return is_button;
+        } else if hit(self.areas.params.area, column, row) {
+            self.focus_block(AppBlock::EndpointsReq);
+            let row_count = self.tab().form_rows().len();
+            if let Some(index) = self.areas.params.row_at(column, row).filter(|&index| index < row_count) {
+                self.tab_mut().focused_endpoint_field = Some(EndpointField::Param(index));
+            }
+        } else if hit(self.areas.request, column, row) {
+            self.focus_block(AppBlock::EndpointsReq);
+        } else if hit(self.areas.response.area, column, row) {
+            self.focus_block(AppBlock::EndpointsRes);
+            let line_count = self.tab().tree_lines().map_or(0, |lines| lines.len());
+            if let Some(index) = self.areas.response.row_at(column, row).filter(|&index| index < line_count) {
+                // Clicking the selected node again folds or unfolds it
+                if self.tab().tree.selected == index {
+                    self.toggle_response_node();
+                } else {
+                    self.tab_mut().tree.selected = index;
+                }
+            }
         }
+        false
     }
 
-    pub fn select_previous_endpoint(&mut self) {
-        if self.connected {
-            if self.selected_endpoint > 0 {
-                self.selected_endpoint -= 1;
+    /// Mouse wheel over the Response block, the endpoint list or the docs
+    pub fn scroll(&mut self, column: u16, row: u16, down: bool) {
+        if hit(self.areas.response.area, column, row) {
+            for _ in 0..WHEEL_LINES {
+                if down {
+                    self.scroll_response_down();
+                } else {
+                    self.scroll_response_up();
+                }
+            }
+        } else if hit(self.areas.endpoint_list.area, column, row) {
+            self.step_endpoint_selection(if down { 1 } else { -1 });
+        } else if hit(self.areas.docs, column, row) {
+            self.docs_scroll = if down {
+                self.docs_scroll.saturating_add(WHEEL_LINES as u16)
+            } else {
+                self.docs_scroll.saturating_sub(WHEEL_LINES as u16)
+            };
+        }
+    }
+
+    fn focus_block(&mut self, block: AppBlock) {
+        self.text_cursor = None;
+        if self.current_block != block {
+            self.endpoint_filter_editing = false;
+        }
+        // Tab loads the selected endpoint when leaving Settings, so a click
+        // straight into another block has to as well
+        if block != AppBlock::Settings {
+            self.load_selected_endpoint();
+        }
+        self.current_block = block;
+    }
+
+    // Endpoint selection
+    /// Endpoint list rows grouped by service. With a filter only matching
+    /// endpoints are kept, best matches first within their service.
+    pub fn endpoint_rows(&self) -> Vec<EndpointRow> {
+        let mut groups: Vec<(String, Vec<(i64, usize)>)> = Vec::new();
+
+        for (index, name) in self.endpoints.iter().enumerate() {
+            let Some(metadata) = self.endpoint_data.get(name) else {
+                continue;
+            };
+            let score = [name.as_str(), metadata.service_name.as_str(), &metadata.method_id.to_string()]
+                .iter()
+                .filter_map(|text| fuzzy_score(&self.endpoint_filter, text))
+                .max();
+            let Some(score) = score else {
+                continue;
+            };
+
+            match groups.iter_mut().find(|(service, _)| *service == metadata.service_name) {
+                Some((_, members)) => members.push((score, index)),
+                None => groups.push((metadata.service_name.clone(), vec![(score, index)])),
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (service, mut members) in groups {
+            if !self.endpoint_filter.is_empty() {
+                members.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+            }
+            rows.push(EndpointRow::Service(service));
+            rows.extend(members.into_iter().map(|(_, index)| EndpointRow::Endpoint(index)));
+        }
+        rows
+    }
+
+    /// Endpoint indices in the order they are listed
+    fn visible_endpoints(&self) -> Vec<usize> {
+        self.endpoint_rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                EndpointRow::Endpoint(index) => Some(index),
+                EndpointRow::Service(_) => None,
+            })
+            .collect()
+    }
+
+    fn select_first_visible_endpoint(&mut self) {
+        let visible = self.visible_endpoints();
+        if !visible.contains(&self.tab().selected_endpoint) {
+            if let Some(&first) = visible.first() {
+                self.tab_mut().selected_endpoint = first;
                 self.update_selected_endpoint_data();
             }
         }
     }
 
+    pub fn select_next_endpoint(&mut self) {
+        self.step_endpoint_selection(1);
+    }
+
+    pub fn select_previous_endpoint(&mut self) {
+        self.step_endpoint_selection(-1);
+    }
+
+    fn step_endpoint_selection(&mut self, step: isize) {
+        if !self.connected {
+            return;
+        }
+        let visible = self.visible_endpoints();
+        let next = match visible.iter().position(|&index| index == self.tab().selected_endpoint) {
+            Some(position) => position
+                .checked_add_signed(step)
+                .and_then(|position| visible.get(position)),
+            None => visible.first(),
+        };
+        if let Some(&next) = next {
+            self.tab_mut().selected_endpoint = next;
+            self.update_selected_endpoint_data();
+        }
+    }
+
+    /// Loads the selected endpoint unless the tab already holds it, so
+    /// moving between blocks keeps edited params and the chosen preset
+    fn load_selected_endpoint(&mut self) {
+        if self.tab().loaded_endpoint != Some(self.tab().selected_endpoint) {
+            self.update_selected_endpoint_data();
+        }
+    }
+
     fn update_selected_endpoint_data(&mut self) {
-        if let Some(endpoint) = self.endpoints.get(self.selected_endpoint) {
-            if let Some(metadata) = self.endpoint_data.get(endpoint) {
-                self.method_id = Some(metadata.method_id);
-                self.service_name = Some(metadata.service_name.clone());
-    
-                // Sort params by their names
-                self.params = {
-                    let mut sorted_params = metadata.params.clone();
-                    sorted_params.sort_by(|a, b| a.name.cmp(&b.name));
-                    sorted_params
-                };
-    
-                // Check if there are default values for this method_id
-                if let Some((_, defaults)) = self.param_defaults.iter().find(|(id, _)| *id == metadata.method_id.to_string()) {
-                    // Create a map of default values for easy lookup
-                    let default_map: HashMap<_, _> = defaults.iter().cloned().collect();
-    
-                    // Populate param_values with either the default value or an empty string
-                    self.param_values = self.params.iter()
-                        .map(|param| {
-                            default_map.get(&param.name).cloned().unwrap_or_else(|| "".to_string())
-                        })
-                        .collect();
-                } else {
-                    // If no defaults are found, initialize with empty strings
-                    self.param_values = vec!["".to_string(); self.params.len()];
+        let Some(metadata) = self.selected_metadata() else {
+            return;
+        };
+
+        // Sort params by their names
+        let params = metadata.sorted_params();
+        let method_id = metadata.method_id;
+        let service_name = metadata.service_name.clone();
+        let is_stream = metadata.is_stream;
+
+        let tab = self.tab_mut();
+        tab.loaded_endpoint = Some(tab.selected_endpoint);
+        tab.method_id = Some(method_id);
+        tab.service_name = Some(service_name);
+        tab.params = params;
+        tab.is_stream = is_stream;
+        self.docs_scroll = 0;
+        self.apply_param_set(0);
+    }
+
+    /// Config defaults for the endpoint selected in the active tab
+    fn selected_defaults(&self) -> Option<&EndpointDefaults> {
+        self.tab().method_id?;
+        let endpoint = self.endpoints.get(self.tab().selected_endpoint)?;
+        find_defaults(&self.param_defaults, endpoint, self.endpoint_data.get(endpoint)?)
+    }
+
+    /// Names of the param sets the active tab can switch between
+    pub fn preset_names(&self) -> Vec<String> {
+        match self.selected_defaults() {
+            Some(defaults) => defaults.param_sets().into_iter().map(|(name, _)| name).collect(),
+            None => vec!["default".to_string()],
+        }
+    }
+
+    /// Fills the active tab's param values from one of its param sets.
+    /// Params missing from the set get an example value, optionals are left null.
+    fn apply_param_set(&mut self, index: usize) {
+        let values = self.selected_defaults()
+            .and_then(|defaults| defaults.param_sets().into_iter().nth(index))
+            .map(|(_, values)| values)
+            .unwrap_or_default();
+
+        let examples = Examples::new(&self.registry);
+        let param_values = self.tab().params
+            .iter()
+            .map(|param| {
+                values.iter()
+                    .find(|(name, _)| *name == param.name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_else(|| examples.param_raw(&param.ty))
+            })
+            .collect();
+
+        let tab = &mut self.tabs[self.active_tab];
+        tab.preset_index = index;
+        tab.param_values = param_values;
+        tab.rebuild_forms(&self.registry);
+    }
+
+    fn step_preset(&mut self, step: isize) {
+        let count = self.preset_names().len() as isize;
+        let index = (self.tab().preset_index as isize + step).rem_euclid(count);
+        self.apply_param_set(index as usize);
+    }
+
+    pub fn move_left(&mut self) {
+        match self.current_block {
+            AppBlock::EndpointsReq if self.tab().focused_endpoint_field == Some(EndpointField::Preset) => self.step_preset(-1),
+            AppBlock::EndpointsReq if self.focus_on_plain_text() => {
+                self.edit_focused_text(|line| line.move_left());
+            }
+            AppBlock::EndpointsReq => self.tab_mut().edit_focused_param(|form, path| {
+                if let Some(node) = form.node_mut(path) {
+                    node.step(-1);
                 }
-    
-                self.is_stream = metadata.is_stream;
+            }),
+            AppBlock::EndpointsRes => self.scroll_response_left(),
+            AppBlock::Settings => {
+                self.edit_focused_text(|line| line.move_left());
             }
+            AppBlock::EndpointList => {}
         }
     }
-    
-    
-    
+
+    pub fn move_right(&mut self) {
+        match self.current_block {
+            AppBlock::EndpointsReq if self.tab().focused_endpoint_field == Some(EndpointField::Preset) => self.step_preset(1),
+            AppBlock::EndpointsReq if self.focus_on_plain_text() => {
+                self.edit_focused_text(|line| line.move_right());
+            }
+            AppBlock::EndpointsReq => self.tab_mut().edit_focused_param(|form, path| {
+                if let Some(node) = form.node_mut(path) {
+                    node.step(1);
+                }
+            }),
+            AppBlock::EndpointsRes => self.scroll_response_right(),
+            AppBlock::Settings => {
+                self.edit_focused_text(|line| line.move_right());
+            }
+            AppBlock::EndpointList => {}
+        }
+    }
+
+    /// A focused param text editor that isn't a date or timestamp picker,
+    /// where Left/Right move the cursor
+    fn focus_on_plain_text(&self) -> bool {
+        self.tab().focused_node(|node| matches!(node, FormNode::Text { .. }) && !node.is_picker()) == Some(true)
+    }
+
     pub fn toggle_json_view_mode(&mut self) {
-        if let Some(raw_json) = self.json_data.as_ref() {
-            self.json_view_mode = match self.json_view_mode {
-                JsonViewMode::Pretty => {
-                    let raw_json = serde_json::to_string(
-                        &serde_json::from_str::<serde_json::Value>(raw_json).unwrap_or_default()
-                    ).unwrap_or_else(|_| raw_json.clone());
-                    self.json_data = Some(raw_json);
-                    JsonViewMode::Raw
-                },
-                JsonViewMode::Raw => {
-                    let pretty_json = serde_json::to_string_pretty(
-                        &serde_json::from_str::<serde_json::Value>(raw_json).unwrap_or_default()
-                    ).unwrap_or_else(|_| raw_json.clone());
-                    self.json_data = Some(pretty_json);
-                    JsonViewMode::Pretty
-                },
-            };
+        let tab = self.tab_mut();
+        tab.json_view_mode = match tab.json_view_mode {
+            JsonViewMode::Pretty => JsonViewMode::Raw,
+            JsonViewMode::Raw => JsonViewMode::Pretty,
+        };
+        if let Some(response) = &tab.response {
+            if let Ok(formatted_json) = tab.json_view_mode.format(response) {
+                tab.json_data = Some(formatted_json);
+            }
         }
     }
-    
+
     // Handle connection and disconnection
     pub async fn handle_enter(&mut self) -> Result<()> {
         if self.current_block == AppBlock::Settings {
             match self.focused_settings_field {
                 Some(SettingsField::ConnectButton) => {
-                    if let Err(_err) = self.handle_connect().await {
-                        self.connected = false;
-                        self.json_data = Some(_err.to_string());
-                    }
-                }
-                Some(SettingsField::DisconnectButton) => {
-                    if let Err(_err) = self.handle_disconnect().await {
-                        self.connected = true;
-                        self.json_data = Some(_err.to_string());
+                    if let Err(_err) = self.handle_connect() {
+                        self.tab_mut().show_message(_err.to_string());
                     }
                 }
+                Some(SettingsField::DisconnectButton) => self.handle_disconnect().await,
                 _ => {}
             }
         } else if self.current_block == AppBlock::EndpointsReq {
-            match self.focused_endpoint_field {
+            match self.tab().focused_endpoint_field {
                 Some(EndpointField::ConnectButton) => {
                     if let Err(_err) = self.handle_endpoint_connect().await {
-                        self.endpoint_connected = false;
-                        self.json_data = Some(_err.to_string());
+                        error!("Failed to send request: {:#}", _err);
+                        let tab = self.tab_mut();
+                        tab.endpoint_connected = false;
+                        tab.show_message(_err.to_string());
                     }
                 }
                 Some(EndpointField::DisconnectButton) => {
                     if let Err(_err) = self.handle_endpoint_disconnect().await {
-                        self.endpoint_connected = true;
-                        self.json_data = Some(_err.to_string());
+                        error!("Failed to disconnect endpoint: {:#}", _err);
+                        let tab = self.tab_mut();
+                        tab.endpoint_connected = true;
+                        tab.show_message(_err.to_string());
                     }
                 }
                 Some(EndpointField::JsonToggleButton) => self.toggle_json_view_mode(),
                 _ => {}
             }
+        } else if self.current_block == AppBlock::EndpointsRes {
+            self.toggle_response_node();
         }
 
         Ok(())
     }
 
-    pub async fn handle_connect(&mut self) -> Result<()> {
-        let headers = format!(
-            "0login, 1{}, 2{}, 3User, 424787297130491616, 5android",
-            self.username, self.password
-        );
-        
-        let client = WsClient::new(&self.url, &headers)
-            .await
-            .context("Failed to connect to WebSocket")?;
-        self.client = Some(client);
-    
-        let client = self.client.as_mut().context("WebSocket client is not connected")?;
-        let raw_response = client.recv_raw().await.context("Failed to receive response from WebSocket")?;
-        
-        let formatted_response = match self.json_view_mode {
-            JsonViewMode::Pretty => {
-                serde_json::to_string_pretty(&raw_response).context("Failed to format JSON as pretty")?
-            }
-            JsonViewMode::Raw => {
-                serde_json::to_string(&raw_response).context("Failed to format JSON as raw")?
+    /// Asks the UI loop to connect and log in, which it does off its own task
+    /// so a slow or unreachable server can't freeze the UI. The outcome comes
+    /// back through `connect_finished`.
+    pub fn handle_connect(&mut self) -> Result<()> {
+        if self.connecting {
+            bail!("Already connecting to {}", self.url);
+        }
+        // A second login would reuse seqs the tabs are still waiting on
+        if self.connected {
+            bail!("Already connected to {}, disconnect first", self.url);
+        }
+        // Frames still arriving from an earlier connection are ignored from here on
+        self.connection_id += 1;
+        self.connecting = true;
+        self.connect_request = Some(ConnectRequest {
+            url: self.url.clone(),
+            header: login_header(&self.username, &self.password),
+        });
+        let message = format!("Connecting to {}...", self.url);
+        self.tab_mut().show_message(message);
+        Ok(())
+    }
+
+    /// Takes the outcome of a connect started with `handle_connect`: the
+    /// sender and the login response. Returns whether the connection is used,
+    /// so the caller starts listening on it.
+    pub fn connect_finished(&mut self, connection_id: u64, result: Result<(WsSender, Value)>) -> bool {
+        if connection_id != self.connection_id {
+            return false;
+        }
+        self.connecting = false;
+        let (sender, login) = match result {
+            Ok(connected) => connected,
+            Err(err) => {
+                error!("Failed to connect to {}: {:#}", self.url, err);
+                self.connected = false;
+                self.tab_mut().show_message(format!("{:#}", err));
+                return false;
             }
         };
-        
-        let resp = format!("Connected to {}\n{}", self.url, formatted_response);        
-        self.json_data = Some(resp);
+        let formatted_response = match self.tab().json_view_mode.format(&login) {
+            Ok(formatted) => formatted,
+            Err(err) => format!("Error: {}", err),
+        };
+
+        info!("Connected to {}", self.url);
+        let resp = format!("Connected to {}\n{}", self.url, formatted_response);
+        self.tab_mut().show_message(resp);
+        self.client = Some(sender);
         self.connected = true;
-    
-        Ok(())
+        true
     }
-    
 
-    pub async fn handle_disconnect(&mut self) -> Result<()> {
+    /// Closes the connection. A close that fails or times out is only
+    /// logged: the connection is dropped and the tabs are reset either way.
+    pub async fn handle_disconnect(&mut self) {
         if let Some(client) = self.client.take() {
-            client.close().await?;
+            match tokio::time::timeout(SEND_TIMEOUT, client.close()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => warn!("Failed to close the connection to {}: {:#}", self.url, err),
+                Err(_) => warn!("Timed out closing the connection to {}", self.url),
+            }
         }
-        let resp = format!("Disconnected from to {}", self.url);
-        self.json_data = Some(resp);
+        for tab in &mut self.tabs {
+            tab.endpoint_connected = false;
+            tab.pending_seq = None;
+        }
+        info!("Disconnected from {}", self.url);
+        let resp = format!("Disconnected from {}", self.url);
+        self.tab_mut().show_message(resp);
         self.connected = false;
-        Ok(())
     }
 
-    pub async fn handle_endpoint_connect(&mut self) -> Result<()> { 
+    /// Sends the active tab's request; the response is delivered by the listener task
+    pub async fn handle_endpoint_connect(&mut self) -> Result<()> {
+        let tab = &mut self.tabs[self.active_tab];
         let client = self.client.as_mut().context("WebSocket client is not connected")?;
+        let method_id = tab.method_id.context("Method ID is missing")?;
 
         let mut converted_params = Vec::new();
-        for (param, value) in self.params.iter().zip(self.param_values.iter()) {
+        for (param, value) in tab.params.iter().zip(tab.param_values.iter()) {
             let converted_value = param.ty
                 .convert_value(value)
                 .context(format!("Failed to convert value for parameter: {}", param.name))?;
             converted_params.push(converted_value);
         }
 
-        client.send_req(self.method_id.unwrap(), converted_params).await.context("Failed to send request to WebSocket")?;
-        let raw_response = client.recv_raw().await.context("Failed to receive response from WebSocket")?;
+        let endpoint = self.endpoints.get(tab.selected_endpoint).cloned().unwrap_or_default();
+        let timing = RequestTiming::start(&endpoint);
+        let seq = tokio::time::timeout(SEND_TIMEOUT, client.send_req(method_id, &converted_params))
+            .await
+            .context("Timed out sending the request")?
+            .context("Failed to send request to WebSocket")?;
+        tab.timing = Some(timing);
+        tab.last_request = serde_json::to_value(WsRequest { method: method_id, seq, params: converted_params }).ok();
 
-        let resp = match self.json_view_mode {
-            JsonViewMode::Pretty => {
-                serde_json::to_string_pretty(&raw_response).context("Failed to format JSON as pretty")?
-            }
-            JsonViewMode::Raw => {
-                serde_json::to_string(&raw_response).context("Failed to format JSON as raw")?
-            }
-        };
-        
-        self.endpoint_connected = true;
-        self.json_data = Some(resp); 
+        let params = tab.params
+            .iter()
+            .zip(tab.param_values.iter())
+            .map(|(param, value)| (param.name.clone(), value.clone()))
+            .collect();
+        tab.history_entry = Some(self.history.push(HistoryEntry::new(endpoint, method_id, params)));
+        tab.pending_seq = Some(seq);
+        tab.frames.clear();
+        tab.response_scroll = (0, 0);
+        tab.endpoint_connected = true;
+        tab.show_message(format!("Request sent (seq {}), waiting for response...", seq));
         Ok(())
     }
 
     pub async fn handle_endpoint_disconnect(&mut self) -> Result<()> {
-        self.endpoint_connected = false;
-        self.json_data = None;
+        let tab = self.tab_mut();
+        tab.endpoint_connected = false;
+        tab.pending_seq = None;
+        tab.frames.clear();
+        tab.json_data = None;
+        tab.response = None;
         Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{KeymapConfig, Type};
+    use serde_json::json;
+
+    fn app_state() -> AppState {
+        app_state_with(HashMap::new())
+    }
+
+    fn app_state_with(endpoint_data: HashMap<String, EndpointMetadata>) -> AppState {
+        let history = History::load(std::env::temp_dir().join("endpoint_validator_no_such_history.json"));
+        let keymap = Keymap::from_config(&KeymapConfig::default()).unwrap();
+        let mut endpoint_names: Vec<String> = endpoint_data.keys().cloned().collect();
+        endpoint_names.sort();
+        AppState::new(
+            endpoint_names,
+            endpoint_data,
+            TypeRegistry::default(),
+            Vec::new(),
+            history,
+            String::new(),
+            keymap,
+            LogBuffer::default(),
+        )
+    }
+
+    #[test]
+    fn frames_go_to_the_tab_that_sent_the_request() {
+        let mut app_state = app_state();
+        app_state.new_tab();
+        app_state.tabs[0].pending_seq = Some(1);
+        app_state.tabs[0].is_stream = true;
+        app_state.tabs[1].pending_seq = Some(2);
+        app_state.active_tab = 1;
+
+        app_state.dispatch_frame(json!({"type": "Stream", "original_seq": 1, "data": [1]}));
+        app_state.dispatch_frame(json!({"type": "Stream", "original_seq": 1, "data": [2]}));
+        assert_eq!(app_state.tabs[0].frames.len(), 2);
+        assert!(app_state.tabs[1].frames.is_empty());
+
+        // An abandoned stream and frames without a seq reach no tab
+        app_state.dispatch_frame(json!({"type": "Stream", "original_seq": 7, "data": []}));
+        app_state.dispatch_frame(json!({"type": "Immediate", "params": {}}));
+        assert_eq!(app_state.tabs[0].frames.len(), 2);
+        assert!(app_state.tabs[1].frames.is_empty());
+
+        app_state.dispatch_frame(json!({"type": "Immediate", "seq": 2, "params": {}}));
+        assert_eq!(app_state.tabs[1].frames.len(), 1);
+        assert_eq!(app_state.tabs[1].pending_seq, None);
+    }
+
+    #[test]
+    fn connect_outcomes_of_earlier_attempts_are_ignored() {
+        let mut app_state = app_state();
+        app_state.handle_connect().unwrap();
+        assert!(app_state.connecting);
+        assert!(app_state.connect_request.take().is_some());
+        assert!(app_state.handle_connect().is_err());
+
+        let stale = app_state.connection_id - 1;
+        assert!(!app_state.connect_finished(stale, Err(anyhow::anyhow!("old attempt"))));
+        assert!(app_state.connecting);
+
+        let current = app_state.connection_id;
+        assert!(!app_state.connect_finished(current, Err(anyhow::anyhow!("refused"))));
+        assert!(!app_state.connecting);
+        assert!(!app_state.connected);
+        assert_eq!(app_state.tab().json_data.as_deref(), Some("refused"));
+        assert!(app_state.handle_connect().is_ok());
+    }
+
+    #[test]
+    fn connect_is_refused_while_connected() {
+        let mut app_state = app_state();
+        app_state.connected = true;
+        let id = app_state.connection_id;
+        assert!(app_state.handle_connect().is_err());
+        assert_eq!(app_state.connection_id, id);
+        assert!(app_state.connect_request.is_none());
+    }
+
+    #[tokio::test]
+    async fn disconnect_always_resets_the_tabs() {
+        let mut app_state = app_state();
+        app_state.connected = true;
+        app_state.tabs[0].endpoint_connected = true;
+        app_state.tabs[0].pending_seq = Some(3);
+
+        app_state.handle_disconnect().await;
+        assert!(!app_state.connected);
+        assert!(!app_state.tabs[0].endpoint_connected);
+        assert_eq!(app_state.tabs[0].pending_seq, None);
+        assert!(app_state.handle_connect().is_ok());
+    }
+
+    #[test]
+    fn switching_blocks_keeps_edited_params() {
+        let metadata = EndpointMetadata {
+            service_name: "Users".to_string(),
+            method_id: 200,
+            params: vec![ParameterMetadata { name: "name".to_string(), ty: Type::String }],
+            is_stream: false,
+            description: String::new(),
+            returns: Vec::new(),
+            stream_response: None,
+            json_schema: Value::Null,
+        };
+        let mut app_state = app_state_with(HashMap::from([("GetUser".to_string(), metadata)]));
+        app_state.connected = true;
+        app_state.current_block = AppBlock::Settings;
+        app_state.switch_block();
+        assert_eq!(app_state.tab().param_values, vec!["example".to_string()]);
+
+        app_state.tab_mut().param_values[0] = "edited".to_string();
+        for _ in 0..4 {
+            app_state.switch_block();
+        }
+        app_state.focus_block(AppBlock::EndpointsReq);
+        assert_eq!(app_state.tab().param_values, vec!["edited".to_string()]);
+    }
+
+    #[test]
+    fn stream_log_keeps_the_latest_frames() {
+        let mut tab = RequestTab::new();
+        tab.is_stream = true;
+        for i in 0..MAX_STREAM_FRAMES + 5 {
+            tab.receive_frame(json!(i));
+        }
+        assert_eq!(tab.frames.len(), MAX_STREAM_FRAMES);
+        assert_eq!(tab.frames.front(), Some(&json!(5)));
+        assert_eq!(tab.frames.back(), Some(&json!(MAX_STREAM_FRAMES + 4)));
+    }
+    #[test]
+    fn raw_scroll_stops_at_the_last_u16_line() {
+        let mut tab = RequestTab::new();
+        tab.json_view_mode = JsonViewMode::Raw;
+        tab.json_data = Some("line\n".repeat(70_000));
+        tab.jump_to_line(69_999);
+        assert_eq!(tab.response_scroll.0, u16::MAX);
+    }
+}
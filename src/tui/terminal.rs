@@ -0,0 +1,54 @@
+use anyhow::Result;
+use crossterm::cursor::Show;
+use crossterm::event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use std::io;
+use std::sync::Once;
+
+/// Raw mode, the alternate screen, mouse capture and bracketed paste for as
+/// long as the guard lives. Dropping it, whether the app quit, failed or is
+/// unwinding from a panic, puts the terminal back.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        install_panic_hook();
+        enable_raw_mode()?;
+        // Created before the rest so a failure below still restores raw mode
+        let guard = TerminalGuard;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leaves raw mode and the alternate screen. Errors are ignored: this runs
+/// on the way out, possibly while panicking, when there is nothing left to do.
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste, Show);
+}
+
+/// Restores the terminal before the panic message is printed, which the
+/// alternate screen would otherwise swallow. Only the UI loop runs on the main
+/// thread: a panic in a spawned task is just logged, since the UI keeps
+/// drawing and a message on stderr would garble the screen.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            tracing::error!("{}", info);
+            if std::thread::current().name() == Some("main") {
+                restore_terminal();
+                previous(info);
+            }
+        }));
+    });
+}
@@ -1,45 +1,337 @@
-use crate::tui::state::{AppState, EndpointField, SettingsField, JsonViewMode, AppBlock};
+use crate::tui::export::ExportKind;
+use crate::tui::keymap::Action;
+use crate::tui::line_edit::cursor_position;
+use crate::tui::state::{AppState, EndpointField, EndpointRow, SettingsField, JsonViewMode, AppBlock};
 use crate::tui::widgets::{
     create_button,
     create_input_widget,
+    create_line_input,
     create_json_viewer,
+    create_json_tree,
     create_list_widget,
-}; 
+    create_docs_viewer,
+    create_param_editor,
+    centered_rect,
+};
 use ratatui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     Frame,
-    widgets::{Block, Borders, Paragraph},
-    style::{Color, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs},
+    style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
 };
 
+/// Height of the log pane, borders included
+const LOG_PANE_HEIGHT: u16 = 10;
+
 pub fn draw_ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
+    let log_height = if app_state.logs_open { LOG_PANE_HEIGHT } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(5), Constraint::Min(1), Constraint::Length(2)].as_ref())
+        .constraints([
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(log_height),
+            Constraint::Length(2),
+        ].as_ref())
         .split(f.size());
 
     draw_settings_screen(f, app_state, chunks[0]);
+    draw_tab_bar(f, app_state, chunks[1]);
 
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(chunks[1]);
+        .split(chunks[2]);
 
     draw_endpoints_screen(f, app_state, main_chunks[0]);
     draw_response_screen(f, app_state, main_chunks[1]);
 
-    draw_help_text(f, chunks[2]);
+    if app_state.logs_open {
+        draw_log_pane(f, app_state, chunks[3]);
+    }
+    draw_help_text(f, app_state, chunks[4]);
+
+    if app_state.history_open {
+        draw_history_popup(f, app_state);
+    }
+    if app_state.export_open {
+        draw_export_popup(f, app_state);
+    }
+    if let Some(preset) = &app_state.save_prompt {
+        let area = centered_rect(50, 20, f.size());
+        let area = Rect { height: area.height.min(3), ..area };
+        f.render_widget(Clear, area);
+        let prompt = create_input_widget(" Save params as preset (empty = default, Enter save, Esc cancel) ", preset, true);
+        f.render_widget(prompt, area);
+    }
+    if app_state.help_open {
+        draw_help_popup(f, app_state);
+    }
+    if app_state.stats_open {
+        draw_stats_popup(f, app_state);
+    }
+    if app_state.quit_confirm {
+        let area = centered_rect(40, 20, f.size());
+        let area = Rect { height: area.height.min(3), ..area };
+        f.render_widget(Clear, area);
+        let text = format!("{} streams still running. Quit? (y/n)", app_state.active_streams());
+        let confirm = Paragraph::new(text)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(ratatui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+        f.render_widget(confirm, area);
+    }
+}
+
+/// Heading, the block it describes and its `(keys, description)` lines
+type HelpSection<'a> = (&'a str, Option<AppBlock>, Vec<(String, String)>);
+
+/// Key bindings of the global actions, the navigation actions and then
+/// the keys specific to each block, the focused block's heading highlighted
+fn draw_help_popup<B: Backend>(f: &mut Frame<B>, app_state: &AppState) {
+    let area = centered_rect(70, 85, f.size());
+    f.render_widget(Clear, area);
+
+    let keymap = &app_state.keymap;
+    let keys = |action: Action| keymap.keys(action);
+    let search_key = if keymap.vim { "/".to_string() } else { "type".to_string() };
+    let global: Vec<(String, String)> = Action::ALL
+        .iter()
+        .filter(|action| !matches!(action, Action::CopyPointer | Action::EditHistory))
+        .map(|&action| (keys(action), action.description().to_string()))
+        .collect();
+    let sections: Vec<HelpSection> = vec![
+        ("Global", None, global),
+        ("Settings", Some(AppBlock::Settings), vec![
+            ("Enter".to_string(), "Press Connect or Disconnect".to_string()),
+            ("Delete".to_string(), "Delete the char under the cursor".to_string()),
+        ]),
+        ("Endpoint list", Some(AppBlock::EndpointList), vec![
+            (search_key, "Fuzzy search by endpoint, service or method code".to_string()),
+        ]),
+        ("Request", Some(AppBlock::EndpointsReq), vec![
+            (format!("{}/{}", keys(Action::Left), keys(Action::Right)), "Switch preset, pick enum variant, move a date by a day".to_string()),
+            ("Space".to_string(), "Toggle bool or null, next variant, set a date to now".to_string()),
+            ("+/Insert".to_string(), "Add a list item".to_string()),
+            ("Delete".to_string(), "Delete the char under the cursor, or remove an empty list item".to_string()),
+            ("Enter".to_string(), "Press the focused button".to_string()),
+        ]),
+        ("Response", Some(AppBlock::EndpointsRes), vec![
+            ("Enter".to_string(), "Fold or unfold the selected node".to_string()),
+            ("/ n N".to_string(), "Search, next and previous match".to_string()),
+            (keys(Action::CopyPointer), Action::CopyPointer.description().to_string()),
+        ]),
+        ("Mouse", None, vec![
+            ("Click".to_string(), "Focus a block, field, endpoint or node, press a button".to_string()),
+            ("Wheel".to_string(), "Scroll the response, endpoint list or docs".to_string()),
+        ]),
+        ("History", None, vec![
+            ("Enter".to_string(), "Send the selected request again".to_string()),
+            (keys(Action::EditHistory), Action::EditHistory.description().to_string()),
+        ]),
+    ];
+
+    let key_width = sections
+        .iter()
+        .flat_map(|(_, _, bindings)| bindings.iter().map(|(keys, _)| keys.chars().count()))
+        .max()
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    for (title, block, bindings) in sections {
+        let color = if block.is_some_and(|block| block == app_state.current_block) { Color::Yellow } else { Color::Cyan };
+        lines.push(Spans::from(Span::styled(title, Style::default().fg(color).add_modifier(Modifier::BOLD))));
+        for (keys, description) in bindings {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {:<width$}  ", keys, width = key_width), Style::default().fg(Color::White)),
+                Span::styled(description, Style::default().fg(Color::Gray)),
+            ]));
+        }
+        lines.push(Spans::from(""));
+    }
+
+    let help = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" Key bindings (any key to close) "),
+    );
+    f.render_widget(help, area);
 }
 
-fn draw_help_text<B: Backend>(f: &mut Frame<B>, area: Rect) {
-    let help_text = Paragraph::new("Press Esc to quit | Use Tab key to switch block | Use arrow keys to navigate | Enter to press button")
-        .style(Style::default().fg(Color::Gray))
+fn draw_help_text<B: Backend>(f: &mut Frame<B>, app_state: &AppState, area: Rect) {
+    let keymap = &app_state.keymap;
+    let (text, color) = match &app_state.status_message {
+        Some(message) => (message.clone(), Color::Yellow),
+        None => (
+            format!(
+                "{} help | {} quit | {} switch block | Enter press button | {} new tab | {} history | {} export | {} save params",
+                keymap.keys(Action::Help),
+                keymap.keys(Action::Quit),
+                keymap.keys(Action::NextBlock),
+                keymap.keys(Action::NewTab),
+                keymap.keys(Action::History),
+                keymap.keys(Action::Export),
+                keymap.keys(Action::SaveParams),
+            ),
+            Color::Gray,
+        ),
+    };
+    let help_text = Paragraph::new(text)
+        .style(Style::default().fg(color))
         .alignment(ratatui::layout::Alignment::Center);
     f.render_widget(help_text, area);
 }
 
+/// Newest log lines at the bottom, warnings and errors highlighted
+fn draw_log_pane<B: Backend>(f: &mut Frame<B>, app_state: &AppState, area: Rect) {
+    let lines = app_state.logs.tail(area.height.saturating_sub(2) as usize);
+    let items: Vec<ListItem> = lines
+        .into_iter()
+        .map(|line| {
+            let color = if line.contains("ERROR") {
+                Color::Red
+            } else if line.contains("WARN") {
+                Color::Yellow
+            } else {
+                Color::Gray
+            };
+            ListItem::new(line).style(Style::default().fg(color))
+        })
+        .collect();
+    let title = format!(" Logs ({} hide) ", app_state.keymap.keys(Action::Logs));
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray))
+            .title(title),
+    );
+    f.render_widget(list, area);
+}
+
+fn draw_tab_bar<B: Backend>(f: &mut Frame<B>, app_state: &AppState, area: Rect) {
+    let titles = (0..app_state.tabs.len())
+        .map(|i| {
+            let marker = if app_state.tabs[i].pending_seq.is_some() { "*" } else { "" };
+            Spans::from(Span::raw(format!(" {}{} ", app_state.tab_title(i), marker)))
+        })
+        .collect();
+
+    let tabs = Tabs::new(titles)
+        .select(app_state.active_tab)
+        .style(Style::default().fg(Color::Gray))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, area);
+}
+
+fn draw_history_popup<B: Backend>(f: &mut Frame<B>, app_state: &AppState) {
+    let area = centered_rect(80, 80, f.size());
+    f.render_widget(Clear, area);
+
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Percentage(60), Constraint::Min(1)].as_ref())
+        .split(area);
+
+    let search = create_input_widget(" Search history ", &app_state.history_query, true);
+    f.render_widget(search, popup_chunks[0]);
+
+    let matches = app_state.history_matches();
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|&i| ListItem::new(app_state.history.entries[i].summary()))
+        .collect();
+    let list = List::new(items)
+        .style(Style::default().fg(Color::Gray))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(format!(" History (Enter re-run, {} edit, Esc close) ", app_state.keymap.keys(Action::EditHistory))),
+        )
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Gray));
+    let mut list_state = ListState::default();
+    if !matches.is_empty() {
+        list_state.select(Some(app_state.history_selected));
+    }
+    f.render_stateful_widget(list, popup_chunks[1], &mut list_state);
+
+    let response = matches
+        .get(app_state.history_selected)
+        .and_then(|&i| app_state.history.entries[i].response.as_ref())
+        .and_then(|response| serde_json::to_string_pretty(response).ok());
+    let preview = create_json_viewer(&response, "", " Response ".to_string(), false);
+    f.render_widget(preview, popup_chunks[2]);
+}
+
+fn draw_export_popup<B: Backend>(f: &mut Frame<B>, app_state: &AppState) {
+    let area = centered_rect(40, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let items: Vec<ListItem> = ExportKind::ALL.iter().map(|kind| ListItem::new(kind.label())).collect();
+    let list = List::new(items)
+        .style(Style::default().fg(Color::Gray))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(" Export (Enter write file, Esc close) "),
+        )
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Gray));
+    let mut list_state = ListState::default();
+    list_state.select(Some(app_state.export_selected));
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Latency per endpoint this session, plus frame intervals for streams
+fn draw_stats_popup<B: Backend>(f: &mut Frame<B>, app_state: &AppState) {
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let stats = &app_state.stats;
+    let name_width = stats.latency.keys().map(|name| name.chars().count()).max().unwrap_or_default().max("Endpoint".len());
+    let header = format!(
+        "{:<width$} {:>6} {:>8} {:>8} {:>8} {:>8}   {:>12} {:>12}",
+        "Endpoint", "n", "min", "avg", "p95", "max", "interval avg", "interval p95",
+        width = name_width
+    );
+    let mut lines = vec![Spans::from(Span::styled(header, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))];
+    for (endpoint, latency) in &stats.latency {
+        let Some(summary) = latency.summary() else {
+            continue;
+        };
+        let (interval_avg, interval_p95) = match stats.intervals.get(endpoint).and_then(|intervals| intervals.summary()) {
+            Some(intervals) => (format!("{}ms", intervals.avg), format!("{}ms", intervals.p95)),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        lines.push(Spans::from(format!(
+            "{:<width$} {:>6} {:>8} {:>8} {:>8} {:>8}   {:>12} {:>12}",
+            endpoint,
+            summary.count,
+            format!("{}ms", summary.min),
+            format!("{}ms", summary.avg),
+            format!("{}ms", summary.p95),
+            format!("{}ms", summary.max),
+            interval_avg,
+            interval_p95,
+            width = name_width
+        )));
+    }
+    if lines.len() == 1 {
+        lines.push(Spans::from(Span::styled("No responses yet", Style::default().fg(Color::Gray))));
+    }
+
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" Latency per endpoint, time to response or first frame (any key to close) "),
+    );
+    f.render_widget(popup, area);
+}
+
 fn draw_settings_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState, area: Rect) {
     let is_focused = app_state.current_block == AppBlock::Settings;
     let title = Spans::from(vec![Span::styled(
@@ -66,13 +358,43 @@ fn draw_settings_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
         .margin(1)
         .split(area);
 
-    let url_input = create_input_widget(" URL ", &app_state.url, app_state.focused_settings_field == Some(SettingsField::Url));
-    let username_input = create_input_widget(" Username ", &app_state.username, app_state.focused_settings_field == Some(SettingsField::Username));
-    let password_input = create_input_widget(" Password ", &app_state.password, app_state.focused_settings_field == Some(SettingsField::Password));
+    // Only the focused field of the focused block draws a cursor
+    let cursor_for = |field: SettingsField, text: &str| {
+        (is_focused && app_state.focused_settings_field == Some(field))
+            .then(|| cursor_position(text, app_state.text_cursor))
+    };
+    let password = if app_state.password_visible {
+        app_state.password.clone()
+    } else {
+        "•".repeat(app_state.password.chars().count())
+    };
+    let password_label = if app_state.password_visible { " Password (Ctrl+O hide) " } else { " Password (Ctrl+O show) " };
+
+    let url_input = create_line_input(
+        " URL ",
+        &app_state.url,
+        cursor_for(SettingsField::Url, &app_state.url),
+        app_state.focused_settings_field == Some(SettingsField::Url),
+        settings_chunks[0].width,
+    );
+    let username_input = create_line_input(
+        " Username ",
+        &app_state.username,
+        cursor_for(SettingsField::Username, &app_state.username),
+        app_state.focused_settings_field == Some(SettingsField::Username),
+        settings_chunks[1].width,
+    );
+    let password_input = create_line_input(
+        password_label,
+        &password,
+        cursor_for(SettingsField::Password, &app_state.password),
+        app_state.focused_settings_field == Some(SettingsField::Password),
+        settings_chunks[2].width,
+    );
 
     let connect_button = create_button(
-        "Connect",
-        !app_state.connected,
+        if app_state.connecting { "Connecting..." } else { "Connect" },
+        !app_state.connected && !app_state.connecting,
         app_state.focused_settings_field == Some(SettingsField::ConnectButton),
     );
 
@@ -87,6 +409,14 @@ fn draw_settings_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
     f.render_widget(password_input, settings_chunks[2]);
     f.render_widget(connect_button, settings_chunks[3]);
     f.render_widget(disconnect_button, settings_chunks[4]);
+
+    app_state.areas.settings_fields = vec![
+        (settings_chunks[0], SettingsField::Url),
+        (settings_chunks[1], SettingsField::Username),
+        (settings_chunks[2], SettingsField::Password),
+        (settings_chunks[3], SettingsField::ConnectButton),
+        (settings_chunks[4], SettingsField::DisconnectButton),
+    ];
 }
 
 fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState, area: Rect) {
@@ -95,14 +425,40 @@ fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
         .split(area);
 
+    let list_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+        .split(endpoint_chunks[0]);
+
     let is_focused = app_state.current_block == AppBlock::EndpointList;
+    let docs = create_docs_viewer(
+        if app_state.connected { app_state.selected_metadata() } else { None },
+        app_state.docs_scroll,
+        is_focused,
+    );
+    f.render_widget(docs, list_chunks[1]);
+
     if app_state.connected {
-        let list_widget = create_list_widget(
-            &app_state.endpoints,
-            app_state.selected_endpoint,
-            is_focused,
-        );
-        f.render_widget(list_widget, endpoint_chunks[0]);
+        let endpoint_rows = app_state.endpoint_rows();
+        let selected_row = endpoint_rows
+            .iter()
+            .position(|row| *row == EndpointRow::Endpoint(app_state.tab().selected_endpoint));
+        let rows = endpoint_rows
+            .into_iter()
+            .map(|row| match row {
+                EndpointRow::Service(service) => (service, true),
+                EndpointRow::Endpoint(index) => {
+                    let name = &app_state.endpoints[index];
+                    let code = app_state.endpoint_data.get(name).map(|m| m.method_id).unwrap_or_default();
+                    (format!("  {} ({})", name, code), false)
+                }
+            })
+            .collect();
+
+        let list_widget = create_list_widget(rows, &app_state.endpoint_filter, is_focused);
+        app_state.endpoint_list_state.select(selected_row);
+        f.render_stateful_widget(list_widget, list_chunks[0], &mut app_state.endpoint_list_state);
+        app_state.areas.endpoint_list.update(list_chunks[0], selected_row);
     } else {
         let title = Spans::from(vec![Span::styled(
             " Endpoint List ",
@@ -118,7 +474,7 @@ fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
             }))
             .title(title);
 
-        f.render_widget(empty_block, endpoint_chunks[0]);
+        f.render_widget(empty_block, list_chunks[0]);
     }
 
     let is_focused = app_state.current_block == AppBlock::EndpointsReq;
@@ -139,24 +495,24 @@ fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
 
     f.render_widget(request_block, endpoint_chunks[1]);
 
-    let num_params = app_state.params.len();
-    let num_items = num_params + 4;
-
-    let mut constraints = Vec::new();
-
-    for _ in 0..num_items {
-        constraints.push(Constraint::Length(3));
-    }
-
-    constraints.push(Constraint::Min(1));
+    let preset_names = app_state.preset_names();
+    let text_cursor = app_state.text_cursor;
+    let tab = app_state.tab_mut();
 
     let request_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(constraints.as_slice())
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
         .margin(1)
         .split(endpoint_chunks[1]);
 
-    let service_name_text = match &app_state.service_name {
+    let service_name_text = match &tab.service_name {
         Some(service_name) => service_name.clone(),
         None => String::from(""),
     };
@@ -172,7 +528,7 @@ fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
         .alignment(ratatui::layout::Alignment::Left);
     f.render_widget(service_name_paragraph, request_chunks[0]);
 
-    let method_id_text = match app_state.method_id {
+    let method_id_text = match tab.method_id {
         Some(method_id) => method_id.to_string(),
         None => String::from(""),
     };
@@ -188,54 +544,118 @@ fn draw_endpoints_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState,
         .alignment(ratatui::layout::Alignment::Left);
     f.render_widget(method_id_paragraph, request_chunks[1]);
 
-    for (i, param) in app_state.params.iter().enumerate() {
-        let param_label = format!(" {}:{:?} ", param.name, param.ty);
-        let param_value = app_state.param_values[i].to_string();
-        let param_input = create_input_widget(
-            &param_label,
-            &param_value,
-            app_state.focused_endpoint_field == Some(EndpointField::Param(i)),
-        );
-        f.render_widget(param_input, request_chunks[i + 2]);
-    }
+    let preset_text = format!(
+        "◀ {} ▶  ({}/{})",
+        preset_names.get(tab.preset_index).map(String::as_str).unwrap_or("default"),
+        tab.preset_index + 1,
+        preset_names.len()
+    );
+    let preset_input = create_input_widget(
+        " Preset (←/→ to switch) ",
+        &preset_text,
+        tab.focused_endpoint_field == Some(EndpointField::Preset),
+    );
+    f.render_widget(preset_input, request_chunks[2]);
+
+    let rows = tab.form_rows();
+    let selected_row = match tab.focused_endpoint_field {
+        Some(EndpointField::Param(index)) if is_focused => Some(index.min(rows.len().saturating_sub(1))),
+        _ => None,
+    };
+    let selected_row = selected_row.filter(|_| !rows.is_empty());
+    tab.param_view.select(selected_row);
+    let cursor = selected_row
+        .and_then(|index| rows.get(index))
+        .and_then(|row| row.text_len)
+        .map(|len| text_cursor.map_or(len, |cursor| cursor.min(len)))
+        .unwrap_or_default();
+    let param_editor = create_param_editor(&rows, selected_row, cursor, is_focused);
+    f.render_stateful_widget(param_editor, request_chunks[3], &mut tab.param_view);
 
     // Layout for Connect and Disconnect buttons
     let button_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(request_chunks[request_chunks.len() - 3]);
+        .split(request_chunks[4]);
 
     let connect_button = create_button(
         "Connect",
-        !app_state.endpoint_connected,
-        app_state.focused_endpoint_field == Some(EndpointField::ConnectButton),
+        !tab.endpoint_connected,
+        tab.focused_endpoint_field == Some(EndpointField::ConnectButton),
     );
 
     let disconnect_button = create_button(
         "Disconnect",
-        app_state.endpoint_connected,
-        app_state.focused_endpoint_field == Some(EndpointField::DisconnectButton),
+        tab.endpoint_connected,
+        tab.focused_endpoint_field == Some(EndpointField::DisconnectButton),
     );
 
     f.render_widget(connect_button, button_chunks[0]);
     f.render_widget(disconnect_button, button_chunks[1]);
 
     let json_toggle_button = create_button(
-        if app_state.json_view_mode == JsonViewMode::Pretty {
+        if tab.json_view_mode == JsonViewMode::Pretty {
             " Pretty JSON "
         } else {
             " Raw JSON "
         },
         true,
-        app_state.focused_endpoint_field == Some(EndpointField::JsonToggleButton),
+        tab.focused_endpoint_field == Some(EndpointField::JsonToggleButton),
     );
 
-    f.render_widget(json_toggle_button, request_chunks[request_chunks.len() - 2]);
+    f.render_widget(json_toggle_button, request_chunks[5]);
+
+    let areas = &mut app_state.areas;
+    areas.docs = list_chunks[1];
+    areas.request = endpoint_chunks[1];
+    areas.request_fields = vec![
+        (request_chunks[2], EndpointField::Preset),
+        (button_chunks[0], EndpointField::ConnectButton),
+        (button_chunks[1], EndpointField::DisconnectButton),
+        (request_chunks[5], EndpointField::JsonToggleButton),
+    ];
+    areas.params.update(request_chunks[3], selected_row);
 }
 
 fn draw_response_screen<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState, area: Rect) {    
     let is_focused = app_state.current_block == AppBlock::EndpointsRes;
-    let json_viewer = create_json_viewer(&app_state.json_data, is_focused)
-        .scroll((app_state.response_scroll.0, app_state.response_scroll.1));
-    f.render_widget(json_viewer, area);
+    app_state.response_height = area.height.saturating_sub(2);
+    let search_editing = app_state.search_editing;
+    let tab = app_state.tab_mut();
+    let timing = match &tab.timing {
+        Some(timing) => format!(" [{}]", timing.describe(tab.is_stream)),
+        None => String::new(),
+    };
+
+    let search_status = if search_editing {
+        format!(" /{}_ ", tab.search_query)
+    } else if !tab.search_query.is_empty() {
+        let matches = tab.search_matches();
+        let current = matches.iter().position(|&line| line == tab.current_line());
+        match current {
+            Some(index) => format!(" /{} [{}/{}] ", tab.search_query, index + 1, matches.len()),
+            None => format!(" /{} [{} matches] ", tab.search_query, matches.len()),
+        }
+    } else {
+        String::new()
+    };
+
+    match tab.tree_lines() {
+        Some(lines) => {
+            tab.tree.selected = tab.tree.selected.min(lines.len().saturating_sub(1));
+            tab.tree.view.select(Some(tab.tree.selected));
+            let title = format!(" Response{} (Enter/←/→ fold, y copy pointer, / search){}", timing, search_status);
+            let json_tree = create_json_tree(&lines, &tab.search_query, title, is_focused);
+            f.render_stateful_widget(json_tree, area, &mut tab.tree.view);
+            let selected = tab.tree.selected;
+            app_state.areas.response.update(area, Some(selected));
+        }
+        None => {
+            let title = format!(" Response{} (/ search){}", timing, search_status);
+            let json_viewer = create_json_viewer(&tab.json_data, &tab.search_query, title, is_focused)
+                .scroll((tab.response_scroll.0, tab.response_scroll.1));
+            f.render_widget(json_viewer, area);
+            app_state.areas.response.update(area, None);
+        }
+    }
 }
@@ -0,0 +1,115 @@
+use crate::parser::{EndpointMetadata, ParameterMetadata, Type};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+pub fn create_docs_viewer<'a>(metadata: Option<&EndpointMetadata>, scroll: u16, is_focused: bool) -> Paragraph<'a> {
+    let lines = match metadata {
+        Some(metadata) => endpoint_docs(metadata),
+        None => vec![Spans::from("Select an endpoint to see its documentation")],
+    };
+
+    let title = Spans::from(vec![Span::styled(
+        " Endpoint Docs (PgUp/PgDn to scroll) ",
+        Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }),
+    )]);
+
+    Paragraph::new(Text::from(lines))
+        .style(Style::default().fg(Color::Gray))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray))
+                .title(title),
+        )
+}
+
+fn endpoint_docs<'a>(metadata: &EndpointMetadata) -> Vec<Spans<'a>> {
+    let mut lines = Vec::new();
+
+    lines.push(Spans::from(Span::raw(if metadata.description.is_empty() {
+        "No description".to_string()
+    } else {
+        metadata.description.clone()
+    })));
+
+    lines.push(Spans::default());
+    lines.push(heading("Parameters"));
+    lines.extend(field_table(&metadata.params));
+
+    lines.push(Spans::default());
+    lines.push(heading("Returns"));
+    lines.extend(field_table(&metadata.returns));
+
+    if let Some(stream) = &metadata.stream_response {
+        lines.push(Spans::default());
+        lines.push(heading("Stream payload"));
+        lines.push(Spans::from(Span::styled(format!("  {}", stream), Style::default().fg(Color::Green))));
+        lines.extend(type_fields(stream));
+    }
+
+    lines.push(Spans::default());
+    lines.push(heading("JSON schema"));
+    let schema = serde_json::to_string_pretty(&metadata.json_schema).unwrap_or_default();
+    lines.extend(schema.lines().map(|line| Spans::from(format!("  {}", line))));
+
+    lines
+}
+
+fn heading<'a>(title: &str) -> Spans<'a> {
+    Spans::from(Span::styled(
+        title.to_string(),
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    ))
+}
+
+/// One `name  type` row per field, names padded to a common width.
+/// Enum fields get their allowed variants listed underneath.
+fn field_table<'a>(fields: &[ParameterMetadata]) -> Vec<Spans<'a>> {
+    if fields.is_empty() {
+        return vec![Spans::from("  (none)")];
+    }
+
+    let width = fields.iter().map(|field| field.name.len()).max().unwrap_or_default();
+    let mut lines = Vec::new();
+    for field in fields {
+        lines.push(Spans::from(vec![
+            Span::raw(format!("  {:width$}  ", field.name, width = width)),
+            Span::styled(field.ty.to_string(), Style::default().fg(Color::Green)),
+        ]));
+        if let Some(variants) = enum_variants(&field.ty) {
+            lines.push(Spans::from(Span::styled(
+                format!("  {:width$}    one of: {}", "", variants.join(", "), width = width),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+    lines
+}
+
+/// Fields of a struct-like payload, so stream docs show the frame shape
+fn type_fields<'a>(ty: &Type) -> Vec<Spans<'a>> {
+    match ty {
+        Type::Struct { fields, .. } | Type::DataTable { fields, .. } => {
+            let fields: Vec<ParameterMetadata> = fields
+                .iter()
+                .map(|field| ParameterMetadata { name: field.name.clone(), ty: field.ty.clone() })
+                .collect();
+            field_table(&fields)
+        }
+        Type::Vec(inner) | Type::Optional(inner) => type_fields(inner),
+        _ => Vec::new(),
+    }
+}
+
+fn enum_variants(ty: &Type) -> Option<Vec<String>> {
+    match ty {
+        Type::Enum { variants, .. } => Some(variants.iter().map(|variant| variant.name.clone()).collect()),
+        Type::Vec(inner) | Type::Optional(inner) => enum_variants(inner),
+        _ => None,
+    }
+}
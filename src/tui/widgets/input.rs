@@ -1,11 +1,45 @@
 use ratatui::{
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     widgets::{Block, Borders, Paragraph},
-    text::Span,
+    text::{Span, Spans},
 };
 
 pub fn create_input_widget<'a>(label: &'a str, value: &'a str, is_focused: bool) -> Paragraph<'a> {
-    let block = Block::default()
+    Paragraph::new(value)
+        .style(Style::default().fg(Color::Gray))
+        .block(input_block(label, is_focused))
+}
+
+/// Input field that draws a cursor at char index `cursor` when given. Text
+/// scrolls horizontally to keep the cursor inside a field `width` cells wide.
+pub fn create_line_input<'a>(label: &'a str, value: &str, cursor: Option<usize>, is_focused: bool, width: u16) -> Paragraph<'a> {
+    let paragraph = match cursor {
+        Some(cursor) => {
+            let inner_width = width.saturating_sub(2) as usize;
+            let offset = (cursor + 1).saturating_sub(inner_width) as u16;
+            Paragraph::new(Spans::from(cursor_spans(value, cursor))).scroll((0, offset))
+        }
+        None => Paragraph::new(value.to_string()),
+    };
+    paragraph
+        .style(Style::default().fg(Color::Gray))
+        .block(input_block(label, is_focused))
+}
+
+/// `text` split around the cursor, the char under it drawn reversed
+pub fn cursor_spans(text: &str, cursor: usize) -> Vec<Span<'static>> {
+    let before: String = text.chars().take(cursor).collect();
+    let at = text.chars().nth(cursor).unwrap_or(' ');
+    let after: String = text.chars().skip(cursor + 1).collect();
+    vec![
+        Span::raw(before),
+        Span::styled(at.to_string(), Style::default().add_modifier(Modifier::REVERSED)),
+        Span::raw(after),
+    ]
+}
+
+fn input_block(label: &str, is_focused: bool) -> Block<'_> {
+    Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(if is_focused {
             Color::Yellow // Highlight border when focused
@@ -14,14 +48,6 @@ pub fn create_input_widget<'a>(label: &'a str, value: &'a str, is_focused: bool)
         }))
         .title(Span::styled(
             label,
-            Style::default().fg(if is_focused {
-                Color::Gray // Highlight title when focused
-            } else {
-                Color::Gray
-            }),
-        ));
-
-    Paragraph::new(value)
-        .style(Style::default().fg(Color::Gray))
-        .block(block)
-}
\ No newline at end of file
+            Style::default().fg(Color::Gray),
+        ))
+}
@@ -1,23 +1,122 @@
+use crate::tui::json_tree::{closing, member_count, TreeLine, TreeToken};
 use ratatui::{
-    style::{Color, Style},
-    text::{Span, Spans},
-    widgets::{Block, Borders, Paragraph},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
 };
+use serde_json::Value;
 
-pub fn create_json_viewer<'a>(json_data: &'a Option<String>, is_focused: bool) -> Paragraph<'a> {
-    let json_display = json_data.as_deref().unwrap_or("");
+/// Plain text view of the response, with `search` matches highlighted
+pub fn create_json_viewer<'a>(json_data: &'a Option<String>, search: &str, title: String, is_focused: bool) -> Paragraph<'a> {
+    let lines: Vec<Spans> = json_data
+        .as_deref()
+        .unwrap_or("")
+        .lines()
+        .map(|line| highlight_matches(Spans::from(line), search))
+        .collect();
 
+    Paragraph::new(Text::from(lines))
+        .style(Style::default().fg(Color::Gray))
+        .block(response_block(title, is_focused))
+}
+
+/// Syntax highlighted, collapsible view of a JSON response. Selection and
+/// scrolling are driven by the `ListState` the caller renders it with.
+pub fn create_json_tree<'a>(lines: &[TreeLine], search: &str, title: String, is_focused: bool) -> List<'a> {
+    let items: Vec<ListItem> = lines
+        .iter()
+        .map(|line| ListItem::new(highlight_matches(tree_line_spans(line), search)))
+        .collect();
+
+    List::new(items)
+        .block(response_block(title, is_focused))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+}
+
+fn response_block<'a>(title: String, is_focused: bool) -> Block<'a> {
     let title = Spans::from(vec![Span::styled(
-        " Response ",
+        title,
         Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }),
     )]);
 
-    Paragraph::new(json_display)
-        .style(Style::default().fg(Color::Gray))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }))
-                .title(title),
-        )
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }))
+        .title(title)
+}
+
+/// Splits every span around case-insensitive occurrences of `search` and
+/// paints the occurrences. Matches spanning two spans are not painted.
+fn highlight_matches<'a>(spans: Spans<'a>, search: &str) -> Spans<'a> {
+    if search.is_empty() {
+        return spans;
+    }
+    let needle = search.to_ascii_lowercase();
+    let highlight = Style::default().bg(Color::Yellow).fg(Color::Black);
+
+    let mut result = Vec::new();
+    for span in spans.0 {
+        let content = span.content.to_string();
+        let haystack = content.to_ascii_lowercase();
+        let mut last = 0;
+        for (start, _) in haystack.match_indices(&needle) {
+            if start > last {
+                result.push(Span::styled(content[last..start].to_string(), span.style));
+            }
+            let end = start + needle.len();
+            result.push(Span::styled(content[start..end].to_string(), span.style.patch(highlight)));
+            last = end;
+        }
+        if last < content.len() {
+            result.push(Span::styled(content[last..].to_string(), span.style));
+        }
+    }
+    Spans::from(result)
+}
+
+fn tree_line_spans<'a>(line: &TreeLine) -> Spans<'a> {
+    let punctuation = Style::default().fg(Color::Gray);
+    let mut spans = vec![Span::raw("  ".repeat(line.depth))];
+
+    if let Some(key) = &line.key {
+        spans.push(Span::styled(serde_json::to_string(key).unwrap_or_default(), Style::default().fg(Color::Cyan)));
+        spans.push(Span::styled(": ", punctuation));
+    }
+
+    match &line.token {
+        TreeToken::Open { bracket, len, collapsed } => {
+            let count = member_count(*bracket, *len);
+            if *collapsed {
+                spans.push(Span::styled(format!("{}…{}", bracket, closing(*bracket)), punctuation));
+                push_comma(&mut spans, line.trailing_comma);
+                spans.push(Span::styled(format!(" {}", count), Style::default().fg(Color::DarkGray)));
+            } else {
+                spans.push(Span::styled(bracket.to_string(), punctuation));
+                spans.push(Span::styled(format!(" {}", count), Style::default().fg(Color::DarkGray)));
+            }
+            return Spans::from(spans);
+        }
+        TreeToken::Close(bracket) => spans.push(Span::styled(bracket.to_string(), punctuation)),
+        TreeToken::Scalar(value) => spans.push(scalar_span(value)),
+    }
+
+    push_comma(&mut spans, line.trailing_comma);
+    Spans::from(spans)
+}
+
+fn push_comma(spans: &mut Vec<Span>, trailing_comma: bool) {
+    if trailing_comma {
+        spans.push(Span::styled(",", Style::default().fg(Color::Gray)));
+    }
+}
+
+fn scalar_span<'a>(value: &Value) -> Span<'a> {
+    let color = match value {
+        Value::String(_) => Color::Green,
+        Value::Number(_) => Color::LightBlue,
+        Value::Bool(_) => Color::Magenta,
+        Value::Null => Color::DarkGray,
+        Value::Array(_) | Value::Object(_) => Color::Gray,
+    };
+    Span::styled(value.to_string(), Style::default().fg(color))
 }
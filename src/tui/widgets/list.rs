@@ -4,38 +4,41 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem},
 };
 
+/// Builds the endpoint list from `(label, is_heading)` rows. Headings are
+/// drawn bold, the selected row is highlighted through the `ListState`.
 pub fn create_list_widget<'a>(
-    items: &'a [String],
-    selected: usize,
+    rows: Vec<(String, bool)>,
+    filter: &str,
     is_focused: bool,
 ) -> List<'a> {
-    let visible_items = &items[selected as usize..];
-    let list_items: Vec<ListItem> = visible_items
-        .iter()
-        .enumerate()
-        .map(|(i, item)| {
-            let style = if i == 0 {
-                Style::default().bg(Color::Blue).fg(Color::Gray)
+    let list_items: Vec<ListItem> = rows
+        .into_iter()
+        .map(|(label, is_heading)| {
+            let style = if is_heading {
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(Color::Gray)
             };
-            ListItem::new(Span::styled(item.clone(), style))
+            ListItem::new(Span::styled(label, style))
         })
         .collect();
 
+    let title = if filter.is_empty() {
+        " Endpoints (type to search) ".to_string()
+    } else {
+        format!(" Endpoints /{} ", filter)
+    };
     let title = Spans::from(vec![Span::styled(
-        "Endpoints",
+        title,
         Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }),
     )]);
 
-    let list = List::new(list_items)
+    List::new(list_items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }))
                 .title(title),
         )
-        .highlight_style(Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD));
-
-    list
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Gray).add_modifier(Modifier::BOLD))
 }
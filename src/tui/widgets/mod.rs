@@ -2,8 +2,14 @@ mod input;
 mod button;
 mod list;
 mod json_viewer;
+mod docs;
+mod param_editor;
+mod popup;
 
 pub use input::*;
 pub use button::*;
 pub use list::*;
-pub use json_viewer::*;
\ No newline at end of file
+pub use json_viewer::*;
+pub use docs::*;
+pub use param_editor::*;
+pub use popup::*;
@@ -0,0 +1,52 @@
+use crate::tui::param_form::FormRow;
+use crate::tui::widgets::cursor_spans;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem},
+};
+
+/// Builds the params form, one line per editor row, nested rows indented
+/// under their parent. The selected row shows which keys its editor takes,
+/// and the cursor at char index `cursor` when it is a text editor.
+pub fn create_param_editor<'a>(rows: &[FormRow], selected: Option<usize>, cursor: usize, is_focused: bool) -> List<'a> {
+    let label_width = rows
+        .iter()
+        .map(|row| row.depth * 2 + row.label.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let list_items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let label = format!("{}{}", "  ".repeat(row.depth), row.label);
+            let mut spans = vec![
+                Span::styled(format!("{:<width$}  ", label, width = label_width), Style::default().fg(Color::Cyan)),
+            ];
+            if selected == Some(i) && row.text_len.is_some() {
+                spans.extend(cursor_spans(&row.value, cursor));
+            } else {
+                spans.push(Span::styled(row.value.clone(), Style::default().fg(Color::White)));
+            }
+            if selected == Some(i) && !row.hint.is_empty() {
+                spans.push(Span::styled(format!("   {}", row.hint), Style::default().fg(Color::DarkGray)));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+
+    let title = Spans::from(vec![Span::styled(
+        " Params (Del removes a list item) ",
+        Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }),
+    )]);
+
+    List::new(list_items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(if is_focused { Color::Yellow } else { Color::Gray }))
+                .title(title),
+        )
+        .highlight_style(Style::default().bg(Color::Blue).add_modifier(Modifier::BOLD))
+}
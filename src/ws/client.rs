@@ -1,73 +1,260 @@
 use anyhow::{anyhow, Context, Result};
+use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
 use reqwest::header::HeaderValue;
 use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+use tracing::{debug, info, info_span, trace, warn, Span};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// When each request still waiting for its response was sent, by seq
+type SentAt = Arc<Mutex<HashMap<u32, Instant>>>;
+
+/// Requests unanswered for this long are forgotten, well past the response
+/// timeouts of the runners, so requests that never get an answer don't pile up
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Numbers the connections of this process, to tell their logs apart
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
 
 pub struct WsClient {
-    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    sender: WsSender,
+    receiver: WsReceiver,
+}
+
+/// Write half of a connection, owns the request sequence counter
+pub struct WsSender {
+    sink: SplitSink<WsStream, Message>,
     seq: u32,
+    span: Span,
+    sent_at: SentAt,
+    pruned_at: Instant,
 }
 
+/// Read half of a connection, can be moved into a task of its own
+pub struct WsReceiver {
+    stream: SplitStream<WsStream>,
+    span: Span,
+    sent_at: SentAt,
+}
 
 #[derive(Serialize)]
-struct WsRequest<T: Serialize> {
-    method: u32,
-    seq: u32,
-    params: T,
+pub struct WsRequest<T: Serialize> {
+    pub method: u32,
+    pub seq: u32,
+    pub params: T,
+}
+
+/// Value of the `Sec-WebSocket-Protocol` header used to log in
+pub fn login_header(username: &str, password: &str) -> String {
+    format!(
+        "0login, 1{}, 2{}, 3User, 424787297130491616, 5android",
+        username, password
+    )
+}
+
+/// The login header with the password left out, safe to log
+pub fn redact_login_header(header: &str) -> String {
+    // The password runs from the `2` field to the fixed `3User` one
+    match (header.find(", 2"), header.rfind(", 3")) {
+        (Some(start), Some(end)) if start < end => format!("{}, 2***{}", &header[..start], &header[end..]),
+        _ => "***".to_string(),
+    }
+}
+
+/// Returns the sequence number of the request a response frame belongs to.
+/// Immediate responses and errors carry `seq`, stream frames carry `original_seq`.
+pub fn response_seq(frame: &serde_json::Value) -> Option<u32> {
+    frame
+        .get("seq")
+        .or_else(|| frame.get("original_seq"))
+        .and_then(|seq| seq.as_u64())
+        .map(|seq| seq as u32)
+}
+
+/// Error code of an error response frame, `None` for any other frame.
+/// Errors without a numeric code report `Some(None)`.
+pub fn response_error_code(frame: &serde_json::Value) -> Option<Option<i64>> {
+    (frame.get("type").and_then(|ty| ty.as_str()) == Some("Error"))
+        .then(|| frame.get("code").and_then(|code| code.as_i64()))
 }
 
 impl WsClient {
     pub async fn new(connect_addr: &str, header: &str) -> Result<Self> {
         let mut req = <&str as IntoClientRequest>::into_client_request(connect_addr)
             .context("Failed to create client request")?;
-        
+
         req.headers_mut()
             .insert("Sec-WebSocket-Protocol", HeaderValue::from_str(header)
             .context("Invalid header value")?);
 
-        let (ws_stream, _) = connect_async(req).await.context("Failed to connect to endpoint")?;
+        let span = info_span!("ws", conn = CONNECTIONS.fetch_add(1, Ordering::Relaxed) + 1, url = %connect_addr);
+        info!(parent: &span, protocol = %redact_login_header(header), "Connecting");
+        let started = Instant::now();
+        let (ws_stream, response) = match connect_async(req).await {
+            Ok(connected) => connected,
+            Err(err) => {
+                warn!(parent: &span, error = %err, "Handshake failed");
+                return Err(err).context("Failed to connect to endpoint");
+            }
+        };
+        info!(
+            parent: &span,
+            status = %response.status(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Handshake complete"
+        );
+
+        let (sink, stream) = ws_stream.split();
+        let sent_at = SentAt::default();
         Ok(Self {
-            stream: ws_stream,
-            seq: 0,
+            sender: WsSender { sink, seq: 0, span: span.clone(), sent_at: Arc::clone(&sent_at), pruned_at: Instant::now() },
+            receiver: WsReceiver { stream, span, sent_at },
         })
     }
 
-    pub async fn send_req(&mut self, method: u32, params: impl Serialize) -> Result<()> {
+    /// Sends a request and returns the sequence number it was tagged with
+    pub async fn send_req(&mut self, method: u32, params: impl Serialize) -> Result<u32> {
+        self.sender.send_req(method, params).await
+    }
+
+    pub async fn recv_raw(&mut self) -> Result<serde_json::Value> {
+        self.receiver.recv_raw().await
+    }
+
+    pub async fn close(self) -> Result<()> {
+        self.sender.close().await
+    }
+
+    /// Splits the client so that requests can be sent while another task is reading
+    pub fn split(self) -> (WsSender, WsReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+impl WsSender {
+    pub async fn send_req(&mut self, method: u32, params: impl Serialize) -> Result<u32> {
         self.seq += 1;
-        let req = serde_json::to_string(&WsRequest{
-            method: method,
+        let req = serde_json::to_string(&WsRequest {
+            method,
             seq: self.seq,
-            params: params,
+            params,
         })
         .context("Failed to serialize request")?;
-        self.stream.send(Message::Text(req)).await.context("Failed to send request")?;
+        debug!(parent: &self.span, method, seq = self.seq, bytes = req.len(), "Sending request");
+        trace!(parent: &self.span, body = %req);
+
+        {
+            let now = Instant::now();
+            let mut sent_at = lock(&self.sent_at);
+            if now - self.pruned_at >= PENDING_TIMEOUT {
+                forget_unanswered(&mut sent_at, now);
+                self.pruned_at = now;
+            }
+            sent_at.insert(self.seq, now);
+        }
+        if let Err(err) = self.sink.send(Message::Text(req)).await {
+            warn!(parent: &self.span, seq = self.seq, error = %err, "Failed to send request");
+            return Err(err).context("Failed to send request");
+        }
+        Ok(self.seq)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        info!(parent: &self.span, "Closing connection");
+        lock(&self.sent_at).clear();
+        self.sink.close().await.context("Failed to close connection")?;
         Ok(())
     }
+}
 
+impl WsReceiver {
     pub async fn recv_raw(&mut self) -> Result<serde_json::Value> {
-        // Get the next message from the stream, or return an error if the connection is closed
-        let msg = self
-            .stream
-            .next()
-            .await
-            .ok_or_else(|| anyhow!("Connection closed"))?
-            .context("Failed to receive message")?;
-    
-        let json_value = match msg {
-            Message::Text(text) => {
-                serde_json::from_str(&text).context("Failed to parse received message as JSON")?
-            }
-            _ => return Err(anyhow!("Received unexpected non-text message")),
+        loop {
+            // Get the next message from the stream, or return an error if the connection is closed
+            let msg = match self.stream.next().await {
+                Some(Ok(msg)) => msg,
+                Some(Err(err)) => {
+                    warn!(parent: &self.span, error = %err, "Failed to receive message");
+                    lock(&self.sent_at).clear();
+                    return Err(err).context("Failed to receive message");
+                }
+                None => {
+                    info!(parent: &self.span, "Connection ended");
+                    lock(&self.sent_at).clear();
+                    return Err(anyhow!("Connection closed"));
+                }
+            };
+
+            let json_value = match msg {
+                Message::Text(text) => {
+                    let value: serde_json::Value =
+                        serde_json::from_str(&text).context("Failed to parse received message as JSON")?;
+                    debug!(
+                        parent: &self.span,
+                        seq = response_seq(&value),
+                        bytes = text.len(),
+                        latency_ms = self.latency_ms(&value),
+                        "Received frame"
+                    );
+                    trace!(parent: &self.span, body = %text);
+                    value
+                }
+                // Pings are answered by tungstenite itself, nothing to surface
+                Message::Ping(_) | Message::Pong(_) => continue,
+                Message::Close(frame) => {
+                    info!(parent: &self.span, frame = ?frame, "Connection closed by the server");
+                    lock(&self.sent_at).clear();
+                    return Err(anyhow!("Connection closed"));
+                }
+                _ => return Err(anyhow!("Received unexpected non-text message")),
+            };
+
+            return Ok(json_value);
+        }
+    }
+
+    /// Time since the request a frame answers was sent. The immediate
+    /// response or error ends the wait; stream frames after it have none.
+    fn latency_ms(&self, frame: &serde_json::Value) -> Option<u64> {
+        let mut sent_at = lock(&self.sent_at);
+        let sent = match frame.get("seq").and_then(|seq| seq.as_u64()) {
+            Some(seq) => sent_at.remove(&(seq as u32)),
+            None => sent_at.get(&response_seq(frame)?).copied(),
         };
-    
-        Ok(json_value)
+        sent.map(|sent| sent.elapsed().as_millis() as u64)
     }
-    
-    pub async fn close(mut self) -> Result<()> {
-        self.stream.close(None).await.context("Failed to close connection")?;
-        Ok(())
+}
+
+fn lock(sent_at: &SentAt) -> MutexGuard<'_, HashMap<u32, Instant>> {
+    sent_at.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Drops the requests sent `PENDING_TIMEOUT` or longer before `now`
+fn forget_unanswered(sent_at: &mut HashMap<u32, Instant>, now: Instant) {
+    sent_at.retain(|_, sent| now - *sent < PENDING_TIMEOUT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unanswered_requests_are_forgotten() {
+        let now = Instant::now() + PENDING_TIMEOUT * 2;
+        let mut sent_at = HashMap::from([
+            (1, now - PENDING_TIMEOUT - Duration::from_millis(1)),
+            (2, now - PENDING_TIMEOUT),
+            (3, now - Duration::from_millis(10)),
+        ]);
+        forget_unanswered(&mut sent_at, now);
+        assert_eq!(sent_at.keys().copied().collect::<Vec<_>>(), vec![3]);
     }
 }